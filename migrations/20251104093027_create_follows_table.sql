@@ -0,0 +1,12 @@
+-- Migration 0008: Create follows table
+
+CREATE TABLE follows (
+    follower_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    followee_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (follower_id, followee_id),
+    CHECK (follower_id <> followee_id)
+);
+
+-- The primary key covers "who does X follow"; this index covers "who follows X"
+CREATE INDEX idx_follows_followee_id ON follows(followee_id);
@@ -0,0 +1,22 @@
+-- Migration 0010: Create favorites table and denormalised favorites count
+
+CREATE TABLE favorites (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    article_id UUID NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, article_id)
+);
+
+-- The primary key covers "what did X favorite"; this index covers "who favorited X"
+CREATE INDEX idx_favorites_article_id ON favorites(article_id);
+
+ALTER TABLE articles
+ADD COLUMN favorites_count INTEGER NOT NULL DEFAULT 0 CHECK (favorites_count >= 0);
+
+-- Favoriting must not bump updated_at, so only content edits fire the trigger
+DROP TRIGGER update_articles_updated_at ON articles;
+
+CREATE TRIGGER update_articles_updated_at
+    BEFORE UPDATE OF slug, title, description, body ON articles
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,16 @@
+-- Migration 0011: Create tags and article_tags tables
+
+CREATE TABLE tags (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(64) UNIQUE NOT NULL CHECK (name = LOWER(name) AND name <> ''),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE article_tags (
+    article_id UUID NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (article_id, tag_id)
+);
+
+-- The primary key covers "tags of an article"; this index covers "articles with a tag"
+CREATE INDEX idx_article_tags_tag_id ON article_tags(tag_id);
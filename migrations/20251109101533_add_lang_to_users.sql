@@ -0,0 +1,4 @@
+-- Migration 0013: Add preferred language to users, used to localise emails
+
+ALTER TABLE users
+ADD COLUMN lang VARCHAR(8) NOT NULL DEFAULT 'en' CHECK (lang IN ('en', 'de', 'fr'));
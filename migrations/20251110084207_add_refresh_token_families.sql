@@ -0,0 +1,13 @@
+-- Migration 0014: Group refresh tokens into rotation families
+-- Every login starts a family; each rotation adds a child token to it. On reuse only the
+-- compromised family is revoked instead of every session of the user.
+
+ALTER TABLE refresh_tokens
+ADD COLUMN family_id UUID NOT NULL DEFAULT uuid_generate_v4(),
+ADD COLUMN parent_id UUID REFERENCES refresh_tokens(id) ON DELETE SET NULL;
+
+-- Existing tokens each become a family of their own
+UPDATE refresh_tokens
+SET family_id = id;
+
+CREATE INDEX idx_refresh_tokens_family_id ON refresh_tokens(family_id);
@@ -0,0 +1,21 @@
+-- Migration 0015: Store SHA-256 digests of tokens instead of the tokens themselves
+-- Existing tokens are hashed in place, so they keep working: the application hashes the
+-- presented token the same way (lowercase hex of the SHA-256 of its UTF-8 bytes).
+
+ALTER TABLE refresh_tokens RENAME COLUMN token TO token_hash;
+UPDATE refresh_tokens SET token_hash = encode(sha256(convert_to(token_hash, 'UTF8')), 'hex');
+ALTER TABLE refresh_tokens ALTER COLUMN token_hash TYPE VARCHAR(64);
+ALTER INDEX idx_refresh_tokens_token RENAME TO idx_refresh_tokens_token_hash;
+
+ALTER TABLE email_verification_tokens RENAME COLUMN token TO token_hash;
+UPDATE email_verification_tokens SET token_hash = encode(sha256(convert_to(token_hash, 'UTF8')), 'hex');
+ALTER TABLE email_verification_tokens ALTER COLUMN token_hash TYPE VARCHAR(64);
+ALTER INDEX idx_email_verification_tokens_token RENAME TO idx_email_verification_tokens_token_hash;
+
+ALTER TABLE password_reset_tokens RENAME COLUMN token TO token_hash;
+UPDATE password_reset_tokens SET token_hash = encode(sha256(convert_to(token_hash, 'UTF8')), 'hex');
+ALTER TABLE password_reset_tokens ALTER COLUMN token_hash TYPE VARCHAR(64);
+ALTER INDEX idx_password_reset_tokens_token RENAME TO idx_password_reset_tokens_token_hash;
+
+-- Emails that were already delivered don't need their token anymore
+UPDATE email_outbox SET email = email - 'token' WHERE status <> 'pending';
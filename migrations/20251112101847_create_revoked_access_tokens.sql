@@ -0,0 +1,17 @@
+-- Migration 0016: Revocation list for access tokens
+-- Access tokens are checked against this list by `jti` until they expire; expired entries are
+-- pruned. Refresh tokens remember the access token issued with them, so revoking a session can
+-- revoke its access tokens too.
+
+CREATE TABLE revoked_access_tokens (
+    jti UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_revoked_access_tokens_expires_at ON revoked_access_tokens(expires_at);
+
+ALTER TABLE refresh_tokens
+ADD COLUMN access_token_jti UUID,
+ADD COLUMN access_token_expires_at TIMESTAMPTZ;
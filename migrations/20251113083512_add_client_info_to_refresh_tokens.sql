@@ -0,0 +1,9 @@
+-- Migration 0017: Remember which client each session belongs to
+-- Recorded at login and on every refresh, so users can recognise their sessions when listing
+-- them. Sessions are found from the caller's access token by its `jti`.
+
+ALTER TABLE refresh_tokens
+ADD COLUMN user_agent TEXT,
+ADD COLUMN ip_address VARCHAR(45);
+
+CREATE INDEX idx_refresh_tokens_access_token_jti ON refresh_tokens(access_token_jti);
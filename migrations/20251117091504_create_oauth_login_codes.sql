@@ -0,0 +1,13 @@
+-- Migration 0020: One-time codes that hand a finished external login to the frontend
+-- The callback redirects the browser to the frontend with the code instead of the tokens; the
+-- frontend exchanges it once, shortly after. Codes are looked up by their SHA-256 digest.
+
+CREATE TABLE oauth_login_codes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    code_hash VARCHAR(64) UNIQUE NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_oauth_login_codes_expires_at ON oauth_login_codes(expires_at);
@@ -0,0 +1,5 @@
+-- Migration 0021: Case-insensitive lookup of users by email
+-- Providers may report an address in another case than it was registered with; external logins
+-- match accounts on lower(email), which this index serves.
+
+CREATE INDEX idx_users_email_lower ON users(lower(email));
@@ -0,0 +1,145 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{
+    Algorithm,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+};
+use sha2::{Digest, Sha256};
+use simple_asn1::{ASN1Block, OID, from_der, oid};
+
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum JwkError {
+    /// not a PEM file
+    Pem(#[from] pem::PemError),
+    /// not a DER encoded public key
+    Der(#[from] simple_asn1::ASN1DecodeErr),
+    /// malformed public key
+    Malformed,
+    /// not a {0:?} public key
+    KeyType(Algorithm),
+}
+
+/// Builds the public JWK for a PEM public key, as published on the JWKS endpoint.
+///
+/// Accepts SubjectPublicKeyInfo (`PUBLIC KEY`) files for all algorithms and PKCS#1
+/// (`RSA PUBLIC KEY`) files for RS256. The `kid` is left unset, see [`thumbprint`].
+pub fn public_jwk(algorithm: Algorithm, public_pem: &[u8]) -> Result<Jwk, JwkError> {
+    let pem = pem::parse(public_pem)?;
+    let wrong_key = || JwkError::KeyType(algorithm);
+
+    let (key_algorithm, parameters) = match (algorithm, pem.tag()) {
+        (Algorithm::RS256, "RSA PUBLIC KEY") => (KeyAlgorithm::RS256, rsa(pem.contents())?),
+        (_, "PUBLIC KEY") => {
+            let (key_oid, curve_oid, key) = subject_public_key_info(pem.contents())?;
+            match algorithm {
+                Algorithm::RS256 if key_oid == oid!(1, 2, 840, 113549, 1, 1, 1) => {
+                    (KeyAlgorithm::RS256, rsa(&key)?)
+                }
+                Algorithm::ES256
+                    if key_oid == oid!(1, 2, 840, 10045, 2, 1)
+                        && curve_oid == Some(oid!(1, 2, 840, 10045, 3, 1, 7))
+                        && key.len() == 65
+                        && key[0] == 0x04 =>
+                {
+                    let parameters = EllipticCurveKeyParameters {
+                        key_type: EllipticCurveKeyType::EC,
+                        curve: EllipticCurve::P256,
+                        x: URL_SAFE_NO_PAD.encode(&key[1..33]),
+                        y: URL_SAFE_NO_PAD.encode(&key[33..]),
+                    };
+                    (
+                        KeyAlgorithm::ES256,
+                        AlgorithmParameters::EllipticCurve(parameters),
+                    )
+                }
+                Algorithm::EdDSA if key_oid == oid!(1, 3, 101, 112) && key.len() == 32 => {
+                    let parameters = OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x: URL_SAFE_NO_PAD.encode(&key),
+                    };
+                    (
+                        KeyAlgorithm::EdDSA,
+                        AlgorithmParameters::OctetKeyPair(parameters),
+                    )
+                }
+                _ => return Err(wrong_key()),
+            }
+        }
+        _ => return Err(wrong_key()),
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    })
+}
+
+/// RFC 7638 thumbprint of a public JWK, used as its `kid` unless one is configured.
+pub fn thumbprint(jwk: &Jwk) -> String {
+    // The required members in lexicographic order; their values never need JSON escaping
+    let canonical = match &jwk.algorithm {
+        AlgorithmParameters::RSA(rsa) => {
+            format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, rsa.e, rsa.n)
+        }
+        AlgorithmParameters::EllipticCurve(ec) => format!(
+            r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+            ec.x, ec.y
+        ),
+        AlgorithmParameters::OctetKeyPair(okp) => {
+            format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, okp.x)
+        }
+        AlgorithmParameters::OctetKey(oct) => format!(r#"{{"k":"{}","kty":"oct"}}"#, oct.value),
+    };
+
+    URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+}
+
+// SEQUENCE { SEQUENCE { key type OID, curve OID or NULL }, BIT STRING key }
+fn subject_public_key_info(der: &[u8]) -> Result<(OID, Option<OID>, Vec<u8>), JwkError> {
+    let blocks = from_der(der)?;
+    let Some(ASN1Block::Sequence(_, info)) = blocks.first() else {
+        return Err(JwkError::Malformed);
+    };
+    let [
+        ASN1Block::Sequence(_, algorithm),
+        ASN1Block::BitString(_, _, key),
+    ] = info.as_slice()
+    else {
+        return Err(JwkError::Malformed);
+    };
+    let key_oid = match algorithm.first() {
+        Some(ASN1Block::ObjectIdentifier(_, oid)) => oid.clone(),
+        _ => return Err(JwkError::Malformed),
+    };
+    let curve_oid = match algorithm.get(1) {
+        Some(ASN1Block::ObjectIdentifier(_, oid)) => Some(oid.clone()),
+        _ => None,
+    };
+
+    Ok((key_oid, curve_oid, key.clone()))
+}
+
+// PKCS#1 RSAPublicKey: SEQUENCE { INTEGER modulus, INTEGER exponent }
+fn rsa(der: &[u8]) -> Result<AlgorithmParameters, JwkError> {
+    let blocks = from_der(der)?;
+    let Some(ASN1Block::Sequence(_, integers)) = blocks.first() else {
+        return Err(JwkError::Malformed);
+    };
+    let [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] = integers.as_slice() else {
+        return Err(JwkError::Malformed);
+    };
+
+    Ok(AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(n.to_bytes_be().1),
+        e: URL_SAFE_NO_PAD.encode(e.to_bytes_be().1),
+    }))
+}
@@ -1,39 +1,222 @@
-use chrono::{Duration, Utc};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+    errors::ErrorKind, jwk::JwkSet,
+};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::jwk::{JwkError, public_jwk, thumbprint};
+use crate::config::{JwtConfig, JwtKeyConfig};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // user_id
     pub exp: usize,  // expiration
     pub iat: usize,  // issued_at
+    pub iss: String, // issuer
+    pub aud: String, // audience
+    pub jti: String, // unique token id
+}
+
+/// A freshly signed access token, along with what's needed to revoke it later.
+pub struct AccessToken {
+    pub token: String,
+    pub jti: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum JwtKeyError {
+    /// could not read JWT key file {0}
+    Read(PathBuf, #[source] std::io::Error),
+    /// JWT key file {0} is not a valid PEM key for the configured algorithm
+    Parse(PathBuf, #[source] jsonwebtoken::errors::Error),
+    /// could not convert JWT key file {0} to a JWK
+    Jwk(PathBuf, #[source] JwkError),
+    /// JWT key id `{0}` is used by more than one key
+    DuplicateKeyId(String),
+}
+
+/// Issues and validates access tokens with the configured algorithm, keys and claims.
+///
+/// Tokens are signed with a single key and name it in their `kid` header. They are verified with
+/// whichever key of the ring their `kid` names, so a key can be rotated while tokens signed with
+/// the previous one are still live.
+pub struct JwtService {
+    header: Header,
+    encoding_key: EncodingKey,
+    verification_keys: HashMap<String, VerificationKey>,
+    // Tokens issued before key ids were introduced carry no `kid`
+    default_key: VerificationKey,
+    jwks: JwkSet,
+    issuer: String,
+    audience: String,
+    ttl: chrono::Duration,
+}
+
+#[derive(Clone)]
+struct VerificationKey {
+    key: DecodingKey,
+    // Only accepts the key's own algorithm, so a token can't pick a weaker one for itself
+    validation: Validation,
+}
+
+impl JwtService {
+    pub fn from_config(config: &JwtConfig) -> Result<Self, JwtKeyError> {
+        let validation = |algorithm| {
+            let mut validation = Validation::new(algorithm);
+            validation.set_issuer(&[&config.issuer]);
+            validation.set_audience(&[&config.audience]);
+            validation.set_required_spec_claims(&["exp", "sub", "iss", "aud"]);
+            validation
+        };
+
+        let mut jwks = JwkSet { keys: Vec::new() };
+        let (encoding_key, decoding_key, key_id) = match &config.key {
+            // The secret must never be published, so it has no JWK and no derived key id
+            JwtKeyConfig::Secret(secret) => (
+                EncodingKey::from_secret(secret.as_bytes()),
+                DecodingKey::from_secret(secret.as_bytes()),
+                config.key_id.clone(),
+            ),
+            JwtKeyConfig::Pem {
+                private_key_file,
+                public_key_file,
+            } => {
+                let private_pem = read_pem(private_key_file)?;
+                let encoding_key = match config.algorithm {
+                    Algorithm::RS256 => EncodingKey::from_rsa_pem(&private_pem),
+                    Algorithm::ES256 => EncodingKey::from_ec_pem(&private_pem),
+                    // The config only pairs PEM files with RS256, ES256 and EdDSA
+                    _ => EncodingKey::from_ed_pem(&private_pem),
+                }
+                .map_err(|err| JwtKeyError::Parse(private_key_file.clone(), err))?;
+
+                let (decoding_key, key_id) = load_public_key(
+                    config.algorithm,
+                    config.key_id.as_deref(),
+                    public_key_file,
+                    &mut jwks,
+                )?;
+                (encoding_key, decoding_key, Some(key_id))
+            }
+        };
+
+        let default_key = VerificationKey {
+            key: decoding_key,
+            validation: validation(config.algorithm),
+        };
+
+        let mut verification_keys = HashMap::new();
+        if let Some(key_id) = &key_id {
+            verification_keys.insert(key_id.clone(), default_key.clone());
+        }
+        for key in &config.verification_keys {
+            let (decoding_key, key_id) = load_public_key(
+                key.algorithm,
+                key.key_id.as_deref(),
+                &key.public_key_file,
+                &mut jwks,
+            )?;
+            let key = VerificationKey {
+                key: decoding_key,
+                validation: validation(key.algorithm),
+            };
+            if verification_keys.insert(key_id.clone(), key).is_some() {
+                return Err(JwtKeyError::DuplicateKeyId(key_id));
+            }
+        }
+
+        let mut header = Header::new(config.algorithm);
+        header.kid = key_id;
+
+        Ok(Self {
+            header,
+            encoding_key,
+            verification_keys,
+            default_key,
+            jwks,
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+            ttl: chrono::Duration::seconds(config.access_token_ttl.as_secs() as i64),
+        })
+    }
+
+    pub fn generate_token(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<AccessToken, jsonwebtoken::errors::Error> {
+        let now = Utc::now();
+        let expires_at = now + self.ttl;
+        let exp = expires_at.timestamp() as usize;
+        let iat = now.timestamp() as usize;
+        let jti = Uuid::new_v4();
+
+        let claims = Claims {
+            sub: user_id.to_string(),
+            exp,
+            iat,
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            jti: jti.to_string(),
+        };
+
+        Ok(AccessToken {
+            token: encode(&self.header, &claims, &self.encoding_key)?,
+            jti,
+            expires_at,
+        })
+    }
+
+    pub fn validate_token(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        let verification_key = match decode_header(token)?.kid {
+            Some(kid) => self
+                .verification_keys
+                .get(&kid)
+                .ok_or(ErrorKind::InvalidToken)?,
+            None => &self.default_key,
+        };
+
+        decode::<Claims>(token, &verification_key.key, &verification_key.validation)
+            .map(|data| data.claims)
+    }
+
+    /// The public keys tokens are verified with, for `/.well-known/jwks.json`.
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+// Loads a PEM public key and adds its JWK to `jwks`, returning the key and its key id
+fn load_public_key(
+    algorithm: Algorithm,
+    key_id: Option<&str>,
+    path: &Path,
+    jwks: &mut JwkSet,
+) -> Result<(DecodingKey, String), JwtKeyError> {
+    let pem = read_pem(path)?;
+    let decoding_key = match algorithm {
+        Algorithm::RS256 => DecodingKey::from_rsa_pem(&pem),
+        Algorithm::ES256 => DecodingKey::from_ec_pem(&pem),
+        _ => DecodingKey::from_ed_pem(&pem),
+    }
+    .map_err(|err| JwtKeyError::Parse(path.to_path_buf(), err))?;
+
+    let mut jwk =
+        public_jwk(algorithm, &pem).map_err(|err| JwtKeyError::Jwk(path.to_path_buf(), err))?;
+    let key_id = key_id.map_or_else(|| thumbprint(&jwk), str::to_string);
+    jwk.common.key_id = Some(key_id.clone());
+    jwks.keys.push(jwk);
+
+    Ok((decoding_key, key_id))
 }
 
-pub fn generate_token(user_id: &Uuid, secret: &str) -> Result<String, jsonwebtoken::errors::Error> {
-    let now = Utc::now();
-    // 1分钟有效期
-    let exp = (now + Duration::minutes(1)).timestamp() as usize;
-    let iat = now.timestamp() as usize;
-
-    let claims = Claims {
-        sub: user_id.to_string(),
-        exp,
-        iat,
-    };
-
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_ref()),
-    )
-}
-
-pub fn validate_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
+fn read_pem(path: &Path) -> Result<Vec<u8>, JwtKeyError> {
+    std::fs::read(path).map_err(|err| JwtKeyError::Read(path.to_path_buf(), err))
 }
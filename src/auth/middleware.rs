@@ -1,73 +1,120 @@
-use crate::{auth::jwt::validate_token, models::User, state::AppState};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{
+    errors::ApiError,
+    models::{ClientInfo, User},
+    state::AppState,
+};
 use axum::{
-    extract::{FromRef, FromRequestParts, Request, State},
-    http::{HeaderMap, StatusCode, request::Parts},
+    extract::{ConnectInfo, FromRef, FromRequestParts, Request, State},
+    http::{HeaderMap, header::USER_AGENT, request::Parts},
     middleware::Next,
     response::Response,
 };
-use tracing::error;
+use tracing::{Span, error};
 use uuid::Uuid;
 
+// Longer user agents are cut off before they are stored
+const MAX_USER_AGENT_LEN: usize = 512;
+
 // For protected routes - requires valid JWT
 pub struct RequireAuth(pub User);
 
 // For optional auth - extracts user if token present
 pub struct OptionalAuth(pub Option<User>);
 
+// For the session endpoints - like RequireAuth, plus the session (refresh token family) the
+// access token was issued for; `None` for access tokens older than session tracking
+pub struct CurrentSession {
+    pub user: User,
+    pub session_id: Option<Uuid>,
+}
+
 impl<S> FromRequestParts<S> for RequireAuth
 where
     AppState: FromRef<S>,
     S: Send + Sync,
 {
-    type Rejection = StatusCode;
+    type Rejection = ApiError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let app_state = AppState::from_ref(state);
-
-        // Extract Authorization header
-        let headers = &parts.headers;
-        let token = extract_token_from_headers(headers).ok_or(StatusCode::UNAUTHORIZED)?;
-
-        // Validate JWT token
-        let jwt_secret = std::env::var("JWT_SECRET").map_err(|err| {
-            error!("Failed to retrieve JWT_SECRET in RequireAuth: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-        let claims = validate_token(&token, &jwt_secret).map_err(|err| {
-            error!("Failed to validate JWT token in RequireAuth: {}", err);
-            StatusCode::UNAUTHORIZED
-        })?;
-
-        // Get user from database
-        let user_id = Uuid::parse_str(&claims.sub).map_err(|err| {
-            error!(
-                "Failed to parse user ID from JWT token in RequireAuth: {}",
-                err
-            );
-            StatusCode::UNAUTHORIZED
-        })?;
-
-        let user = app_state
-            .user_repository
-            .find_by_id(user_id)
-            .await
-            .map_err(|err| {
-                error!("Failed to find user by ID in RequireAuth: {}", err);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?
-            .ok_or(StatusCode::UNAUTHORIZED)?;
+        let (user, _) = authenticate(&app_state, &parts.headers).await?;
 
         Ok(RequireAuth(user))
     }
 }
 
+impl<S> FromRequestParts<S> for CurrentSession
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let app_state = AppState::from_ref(state);
+        let (user, jti) = authenticate(&app_state, &parts.headers).await?;
+
+        let session_id = app_state
+            .refresh_token_repository
+            .find_by_access_token_jti(jti)
+            .await?
+            .map(|refresh_token| refresh_token.family_id);
+
+        Ok(CurrentSession { user, session_id })
+    }
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+    AppState: FromRef<S>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let app_state = AppState::from_ref(state);
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LEN).collect());
+
+        // Only the right-most X-Forwarded-For entry is trustworthy: it was appended by the proxy in
+        // front of us, while the client can put anything in the entries before it
+        let forwarded_for = app_state
+            .config
+            .server
+            .trust_proxy_headers
+            .then(|| parts.headers.get("X-Forwarded-For"))
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+        let ip_address = forwarded_for
+            .or_else(|| {
+                parts
+                    .extensions
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| addr.ip())
+            })
+            .map(|ip| ip.to_string());
+
+        Ok(ClientInfo {
+            user_agent,
+            ip_address,
+        })
+    }
+}
+
 impl<S> FromRequestParts<S> for OptionalAuth
 where
     AppState: FromRef<S>,
     S: Send + Sync,
 {
-    type Rejection = StatusCode;
+    type Rejection = ApiError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let app_state = AppState::from_ref(state);
@@ -80,12 +127,7 @@ where
         };
 
         // Try to validate JWT token
-        let jwt_secret = std::env::var("JWT_SECRET").map_err(|err| {
-            error!("Failed to get JWT secret in OptionalAuth: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-        let claims = match validate_token(&token, &jwt_secret) {
+        let claims = match app_state.jwt.validate_token(&token) {
             Ok(claims) => claims,
             Err(_) => return Ok(OptionalAuth(None)),
         };
@@ -96,27 +138,67 @@ where
             Err(_) => return Ok(OptionalAuth(None)),
         };
 
-        let user = app_state
-            .user_repository
-            .find_by_id(user_id)
-            .await
-            .map_err(|err| {
-                error!("Failed to find user by ID in OptionalAuth: {}", err);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
+        if is_revoked(&app_state, &claims.jti) {
+            return Ok(OptionalAuth(None));
+        }
+
+        let user = app_state.user_repository.find_by_id(user_id).await?;
 
         Ok(OptionalAuth(user))
     }
 }
 
+// Validates the bearer token and loads its user, returning the user and the token's `jti`
+async fn authenticate(app_state: &AppState, headers: &HeaderMap) -> Result<(User, Uuid), ApiError> {
+    // Extract Authorization header
+    let token = extract_token_from_headers(headers).ok_or(ApiError::Unauthorized)?;
+
+    // Validate JWT token
+    let claims = app_state.jwt.validate_token(&token).map_err(|err| {
+        error!("Failed to validate JWT token in RequireAuth: {}", err);
+        ApiError::Unauthorized
+    })?;
+
+    // Get user from database
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|err| {
+        error!(
+            "Failed to parse user ID from JWT token in RequireAuth: {}",
+            err
+        );
+        ApiError::Unauthorized
+    })?;
+
+    if is_revoked(app_state, &claims.jti) {
+        return Err(ApiError::Unauthorized);
+    }
+    let jti = Uuid::parse_str(&claims.jti).map_err(|_| ApiError::Unauthorized)?;
+
+    let user = app_state
+        .user_repository
+        .find_by_id(user_id)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+
+    Ok((user, jti))
+}
+
+// Tokens we issued always carry a UUID `jti`; anything else can't be checked, so it is rejected
+fn is_revoked(state: &AppState, jti: &str) -> bool {
+    Uuid::parse_str(jti).map_or(true, |jti| state.access_token_denylist.is_revoked(&jti))
+}
+
 fn extract_token_from_headers(headers: &HeaderMap) -> Option<String> {
     let auth_header = headers.get("Authorization")?.to_str().ok()?;
 
-    if auth_header.starts_with("Token ") {
-        Some(auth_header[6..].to_string())
-    } else {
-        None
-    }
+    auth_header.strip_prefix("Token ").map(str::to_string)
+}
+
+/// Span of an HTTP request for the trace layer.
+///
+/// Only the path is recorded: query strings carry one-time credentials, like the token of an
+/// email verification link or the code and state of an OAuth callback.
+pub fn request_span<B>(request: &axum::http::Request<B>) -> Span {
+    tracing::info_span!("http_req", method = %request.method(), path = %request.uri().path())
 }
 
 pub async fn track_metrics(State(state): State<AppState>, req: Request, next: Next) -> Response {
@@ -1,4 +1,7 @@
+pub mod jwk;
 pub mod jwt;
+pub mod mfa;
 pub mod middleware;
+pub mod oauth;
 pub mod password;
 pub mod tokens;
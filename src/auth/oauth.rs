@@ -0,0 +1,436 @@
+use std::{
+    collections::HashMap,
+    sync::{PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+
+use oauth2::{
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, EndpointMaybeSet,
+    EndpointNotSet, EndpointSet, ErrorResponse, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl,
+    RequestTokenError, Scope, TokenResponse, TokenUrl, basic::BasicClient, url::Url,
+};
+use openidconnect::{
+    AuthenticationFlow, IssuerUrl, Nonce, TokenResponse as _,
+    core::{CoreClient, CoreProviderMetadata, CoreResponseType},
+};
+use serde::Deserialize;
+
+use crate::config::{OAuthConfig, OAuthProviderConfig, OAuthProviderKind};
+
+// Providers rotate their signing keys, so the discovered metadata (and with it the JWKS) is
+// fetched again after this long
+const METADATA_TTL: Duration = Duration::from_secs(60 * 60);
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// Clients with the authorization and token endpoints set, as the flow needs them
+type OidcClient = CoreClient<
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+type GitHubClient =
+    BasicClient<EndpointSet, EndpointNotSet, EndpointNotSet, EndpointNotSet, EndpointSet>;
+
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum OAuthError {
+    /// invalid URL for identity provider {0}
+    Url(String, #[source] oauth2::url::ParseError),
+    /// no frontend URL to send users back to after signing in
+    MissingFrontendUrl,
+    /// could not build HTTP client
+    HttpClient(#[source] reqwest::Error),
+    /// could not fetch metadata of identity provider
+    Discovery(#[source] BoxError),
+    /// identity provider refused the login
+    Denied(String),
+    /// identity provider is unavailable ({0})
+    Unavailable(String),
+    /// identity provider rejected the authorization code
+    CodeRejected(#[source] BoxError),
+    /// could not redeem authorization code
+    Exchange(#[source] BoxError),
+    /// identity provider did not return an ID token
+    MissingIdToken,
+    /// invalid ID token
+    IdToken(#[source] BoxError),
+    /// could not fetch user from identity provider
+    UserInfo(#[source] reqwest::Error),
+}
+
+impl OAuthError {
+    /// Whether the login itself was bad, e.g. a forged or replayed callback, rather than the
+    /// provider being unreachable or misbehaving.
+    pub fn is_rejection(&self) -> bool {
+        matches!(
+            self,
+            OAuthError::Denied(_)
+                | OAuthError::CodeRejected(_)
+                | OAuthError::MissingIdToken
+                | OAuthError::IdToken(_)
+        )
+    }
+
+    /// The error a provider redirected back with instead of a code, see RFC 6749 4.1.2.1.
+    pub fn from_callback(error: &str) -> Self {
+        match error {
+            // The provider's own failures; everything else, e.g. `access_denied`, is a refusal
+            "server_error" | "temporarily_unavailable" => {
+                OAuthError::Unavailable(error.to_string())
+            }
+            _ => OAuthError::Denied(error.to_string()),
+        }
+    }
+}
+
+// An error response from the token endpoint rejects the code; anything else means the
+// endpoint couldn't be reached or answered nonsense
+fn token_error<RE, T>(err: RequestTokenError<RE, T>) -> OAuthError
+where
+    RE: std::error::Error + Send + Sync + 'static,
+    T: ErrorResponse + Send + Sync + 'static,
+{
+    match err {
+        RequestTokenError::ServerResponse(_) => OAuthError::CodeRejected(err.into()),
+        _ => OAuthError::Exchange(err.into()),
+    }
+}
+
+/// Where to send the user to sign in, and what the callback needs to complete the login.
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub state: String,
+    pub pkce_verifier: String,
+    // OpenID Connect only
+    pub nonce: Option<String>,
+}
+
+/// The account the user signed in with at the provider.
+#[derive(Debug)]
+pub struct ExternalIdentity {
+    // The provider's stable id for the account
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    // Suggestion for the username of a new account
+    pub username: Option<String>,
+}
+
+/// The configured identity providers, by name.
+///
+/// Logins use the authorization code flow with PKCE. OpenID Connect providers prove the
+/// identity with a signed ID token bound to the login by its nonce; GitHub has no ID tokens, so
+/// the account is fetched from its API with the access token instead.
+pub struct OAuthService {
+    providers: HashMap<String, OAuthProvider>,
+}
+
+pub struct OAuthProvider {
+    client_id: ClientId,
+    client_secret: ClientSecret,
+    redirect_url: RedirectUrl,
+    frontend_url: Url,
+    scopes: Vec<String>,
+    kind: ProviderKind,
+    http_client: reqwest::Client,
+}
+
+enum ProviderKind {
+    Oidc {
+        issuer_url: IssuerUrl,
+        // Boxed, the metadata is large
+        metadata: RwLock<Option<(Instant, Box<CoreProviderMetadata>)>>,
+    },
+    GitHub {
+        auth_url: AuthUrl,
+        token_url: TokenUrl,
+        api_url: String,
+    },
+}
+
+impl OAuthService {
+    /// Sets up the providers; callbacks go to `{base_url}/api/auth/oauth/{name}/callback`,
+    /// which has to be registered with each provider.
+    pub fn from_config(config: &OAuthConfig, base_url: &str) -> Result<Self, OAuthError> {
+        // Following redirects from the token endpoint could leak the code, see RFC 6749 10.6
+        let http_client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent("realworld-axum-api")
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(OAuthError::HttpClient)?;
+
+        let frontend_url = config
+            .frontend_url
+            .as_deref()
+            .map(Url::parse)
+            .transpose()
+            .map_err(|err| OAuthError::Url("frontend".to_string(), err))?;
+
+        let providers = config
+            .providers
+            .iter()
+            .map(|provider| {
+                let frontend_url = frontend_url.clone().ok_or(OAuthError::MissingFrontendUrl)?;
+                let oauth_provider =
+                    OAuthProvider::new(provider, base_url, frontend_url, http_client.clone())?;
+                Ok((provider.name.clone(), oauth_provider))
+            })
+            .collect::<Result<_, OAuthError>>()?;
+
+        Ok(Self { providers })
+    }
+
+    pub fn provider(&self, name: &str) -> Option<&OAuthProvider> {
+        self.providers.get(name)
+    }
+}
+
+impl OAuthProvider {
+    fn new(
+        config: &OAuthProviderConfig,
+        base_url: &str,
+        frontend_url: Url,
+        http_client: reqwest::Client,
+    ) -> Result<Self, OAuthError> {
+        let invalid_url = |err| OAuthError::Url(config.name.clone(), err);
+
+        let redirect_url = RedirectUrl::new(format!(
+            "{base_url}/api/auth/oauth/{}/callback",
+            config.name
+        ))
+        .map_err(invalid_url)?;
+
+        let kind = match &config.kind {
+            OAuthProviderKind::Oidc { issuer_url } => ProviderKind::Oidc {
+                issuer_url: IssuerUrl::new(issuer_url.clone()).map_err(invalid_url)?,
+                metadata: RwLock::new(None),
+            },
+            OAuthProviderKind::GitHub {
+                auth_url,
+                token_url,
+                api_url,
+            } => ProviderKind::GitHub {
+                auth_url: AuthUrl::new(auth_url.clone()).map_err(invalid_url)?,
+                token_url: TokenUrl::new(token_url.clone()).map_err(invalid_url)?,
+                api_url: api_url.trim_end_matches('/').to_string(),
+            },
+        };
+
+        Ok(Self {
+            client_id: ClientId::new(config.client_id.clone()),
+            client_secret: ClientSecret::new(config.client_secret.clone()),
+            redirect_url,
+            frontend_url,
+            scopes: config.scopes.clone(),
+            kind,
+            http_client,
+        })
+    }
+
+    pub async fn authorization_request(&self) -> Result<AuthorizationRequest, OAuthError> {
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let scopes = self.scopes.iter().map(|scope| Scope::new(scope.clone()));
+
+        let (url, state, nonce) = match &self.kind {
+            ProviderKind::Oidc { .. } => {
+                // `openid` is always requested
+                let (url, state, nonce) = self
+                    .oidc_client()
+                    .await?
+                    .authorize_url(
+                        AuthenticationFlow::<CoreResponseType>::AuthorizationCode,
+                        CsrfToken::new_random,
+                        Nonce::new_random,
+                    )
+                    .add_scopes(scopes.filter(|scope| scope.as_str() != "openid"))
+                    .set_pkce_challenge(pkce_challenge)
+                    .url();
+                (url, state, Some(nonce.secret().clone()))
+            }
+            ProviderKind::GitHub {
+                auth_url,
+                token_url,
+                ..
+            } => {
+                let (url, state) = self
+                    .github_client(auth_url, token_url)
+                    .authorize_url(CsrfToken::new_random)
+                    .add_scopes(scopes)
+                    .set_pkce_challenge(pkce_challenge)
+                    .url();
+                (url, state, None)
+            }
+        };
+
+        Ok(AuthorizationRequest {
+            url: url.to_string(),
+            state: state.secret().clone(),
+            pkce_verifier: pkce_verifier.secret().clone(),
+            nonce,
+        })
+    }
+
+    /// Where the callback sends the user once signed in, with the code the frontend exchanges
+    /// for the tokens.
+    pub fn frontend_redirect(&self, login_code: &str) -> String {
+        let mut url = self.frontend_url.clone();
+        url.query_pairs_mut().append_pair("code", login_code);
+        url.to_string()
+    }
+
+    /// Redeems the authorization code from the callback for the user's identity.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        pkce_verifier: &str,
+        nonce: Option<&str>,
+    ) -> Result<ExternalIdentity, OAuthError> {
+        let code = AuthorizationCode::new(code.to_string());
+        let pkce_verifier = PkceCodeVerifier::new(pkce_verifier.to_string());
+
+        match &self.kind {
+            ProviderKind::Oidc { .. } => {
+                let client = self.oidc_client().await?;
+                let token_response = client
+                    .exchange_code(code)
+                    .map_err(|err| OAuthError::Exchange(err.into()))?
+                    .set_pkce_verifier(pkce_verifier)
+                    .request_async(&self.http_client)
+                    .await
+                    .map_err(token_error)?;
+
+                let id_token = token_response
+                    .id_token()
+                    .ok_or(OAuthError::MissingIdToken)?;
+                // A login without a nonce can't have come from `authorization_request`
+                let nonce = Nonce::new(nonce.ok_or(OAuthError::MissingIdToken)?.to_string());
+                let claims = id_token
+                    .claims(&client.id_token_verifier(), &nonce)
+                    .map_err(|err| OAuthError::IdToken(err.into()))?;
+
+                Ok(ExternalIdentity {
+                    subject: claims.subject().to_string(),
+                    email: claims.email().map(|email| email.to_string()),
+                    email_verified: claims.email_verified().unwrap_or(false),
+                    username: claims
+                        .preferred_username()
+                        .map(|username| username.to_string()),
+                })
+            }
+            ProviderKind::GitHub {
+                auth_url,
+                token_url,
+                api_url,
+            } => {
+                let token_response = self
+                    .github_client(auth_url, token_url)
+                    .exchange_code(code)
+                    .set_pkce_verifier(pkce_verifier)
+                    .request_async(&self.http_client)
+                    .await
+                    .map_err(token_error)?;
+                let access_token = token_response.access_token().secret();
+
+                self.github_identity(api_url, access_token)
+                    .await
+                    .map_err(OAuthError::UserInfo)
+            }
+        }
+    }
+
+    async fn oidc_client(&self) -> Result<OidcClient, OAuthError> {
+        let ProviderKind::Oidc {
+            issuer_url,
+            metadata,
+        } = &self.kind
+        else {
+            unreachable!("only called for OpenID Connect providers");
+        };
+
+        let cached = metadata
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < METADATA_TTL)
+            .map(|(_, metadata)| (**metadata).clone());
+        let provider_metadata = match cached {
+            Some(provider_metadata) => provider_metadata,
+            None => {
+                let provider_metadata =
+                    CoreProviderMetadata::discover_async(issuer_url.clone(), &self.http_client)
+                        .await
+                        .map_err(|err| OAuthError::Discovery(err.into()))?;
+                *metadata.write().unwrap_or_else(PoisonError::into_inner) =
+                    Some((Instant::now(), Box::new(provider_metadata.clone())));
+                provider_metadata
+            }
+        };
+
+        Ok(CoreClient::from_provider_metadata(
+            provider_metadata,
+            self.client_id.clone(),
+            Some(self.client_secret.clone()),
+        )
+        .set_redirect_uri(self.redirect_url.clone()))
+    }
+
+    fn github_client(&self, auth_url: &AuthUrl, token_url: &TokenUrl) -> GitHubClient {
+        BasicClient::new(self.client_id.clone())
+            .set_client_secret(self.client_secret.clone())
+            .set_auth_uri(auth_url.clone())
+            .set_token_uri(token_url.clone())
+            .set_redirect_uri(self.redirect_url.clone())
+    }
+
+    async fn github_identity(
+        &self,
+        api_url: &str,
+        access_token: &str,
+    ) -> Result<ExternalIdentity, reqwest::Error> {
+        #[derive(Deserialize)]
+        struct GitHubUser {
+            id: u64,
+            login: String,
+        }
+
+        #[derive(Deserialize)]
+        struct GitHubEmail {
+            email: String,
+            primary: bool,
+            verified: bool,
+        }
+
+        let user: GitHubUser = self
+            .http_client
+            .get(format!("{api_url}/user"))
+            .bearer_auth(access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // The profile only shows the public email, if any; the primary one comes from here
+        let emails: Vec<GitHubEmail> = self
+            .http_client
+            .get(format!("{api_url}/user/emails"))
+            .bearer_auth(access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let primary = emails.into_iter().find(|email| email.primary);
+
+        Ok(ExternalIdentity {
+            subject: user.id.to_string(),
+            email_verified: primary.as_ref().is_some_and(|email| email.verified),
+            email: primary.map(|email| email.email),
+            username: Some(user.login),
+        })
+    }
+}
@@ -1,7 +1,96 @@
-use uuid::Uuid;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::{Uuid, Version};
 
-pub fn generate_refresh_token() -> String {
-    // Generate a random UUID and convert to string
-    // This creates a unique, unpredictable token
-    Uuid::new_v4().to_string()
+/// Number of random bytes in a token (256 bits).
+const ENTROPY_BYTES: usize = 32;
+/// Hex digits of the CRC32 checksum appended to a token.
+const CHECKSUM_LEN: usize = 8;
+
+/// The kinds of opaque tokens handed out to clients.
+///
+/// Tokens look like `rt_<64 hex digits of entropy><8 hex digits of CRC32>`. The prefix makes a
+/// leaked token recognisable to secret scanners, and the checksum lets a mistyped or made-up
+/// token be rejected without touching the database.
+///
+/// Tokens issued before that format were plain UUIDv4s. They are stored hashed like any other
+/// token and stay valid until they expire, so they are still accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Refresh,
+    EmailVerification,
+    PasswordReset,
+    MfaChallenge,
+    OAuthLogin,
+}
+
+impl TokenKind {
+    fn prefix(self) -> &'static str {
+        match self {
+            TokenKind::Refresh => "rt_",
+            TokenKind::EmailVerification => "ev_",
+            TokenKind::PasswordReset => "pr_",
+            TokenKind::MfaChallenge => "mc_",
+            TokenKind::OAuthLogin => "ol_",
+        }
+    }
+
+    /// Generates a new token of this kind from the thread-local CSPRNG.
+    pub fn generate(self) -> String {
+        let mut entropy = [0u8; ENTROPY_BYTES];
+        rand::rng().fill_bytes(&mut entropy);
+
+        let mut token =
+            String::with_capacity(self.prefix().len() + 2 * ENTROPY_BYTES + CHECKSUM_LEN);
+        token.push_str(self.prefix());
+        token.push_str(&hex::encode(entropy));
+        let checksum = crc32fast::hash(token.as_bytes());
+        token.push_str(&format!("{:08x}", checksum));
+        token
+    }
+
+    /// Whether `token` has the prefix, length and checksum of a token of this kind, or is a
+    /// token of this kind in the legacy format.
+    pub fn is_well_formed(self, token: &str) -> bool {
+        self.has_checksum(token) || self.is_legacy(token)
+    }
+
+    fn has_checksum(self, token: &str) -> bool {
+        let Some(body) = token.strip_prefix(self.prefix()) else {
+            return false;
+        };
+        if body.len() != 2 * ENTROPY_BYTES + CHECKSUM_LEN
+            || !body.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        {
+            return false;
+        }
+
+        let (payload, checksum) = token.split_at(token.len() - CHECKSUM_LEN);
+        format!("{:08x}", crc32fast::hash(payload.as_bytes())) == checksum
+    }
+
+    // Refresh tokens were hyphenated UUIDs, verification and reset tokens simple ones; MFA
+    // challenges and OAuth login codes never had the legacy format
+    fn is_legacy(self, token: &str) -> bool {
+        let Ok(uuid) = Uuid::try_parse(token) else {
+            return false;
+        };
+        if uuid.get_version() != Some(Version::Random) {
+            return false;
+        }
+
+        match self {
+            TokenKind::Refresh => token == uuid.hyphenated().to_string(),
+            TokenKind::EmailVerification | TokenKind::PasswordReset => {
+                token == uuid.simple().to_string()
+            }
+            TokenKind::MfaChallenge | TokenKind::OAuthLogin => false,
+        }
+    }
+}
+
+/// Hex-encoded SHA-256 digest of a token, which is what gets stored in
+/// and looked up from the database in place of the token itself.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
 }
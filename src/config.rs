@@ -0,0 +1,951 @@
+use std::{env, net::SocketAddr, path::PathBuf, str::FromStr, time::Duration};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use jsonwebtoken::Algorithm;
+use serde::Deserialize;
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+const MIN_JWT_SECRET_LEN: usize = 32;
+// Revoked access tokens stay on the denylist until they expire, so a long lifetime keeps it large
+const MAX_ACCESS_TOKEN_TTL_SECS: u64 = 24 * 60 * 60;
+pub const MFA_ENCRYPTION_KEY_LEN: usize = 32;
+pub const OUTBOX_ENCRYPTION_KEY_LEN: usize = 32;
+
+/// Application configuration, loaded once at startup.
+///
+/// Values come from an optional TOML file (`CONFIG_FILE`, or `config.toml` if present) and are
+/// then overridden by environment variables, so existing `.env` setups keep working.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub jwt: JwtConfig,
+    /// Off unless an encryption key is configured
+    pub mfa: Option<MfaConfig>,
+    pub oauth: OAuthConfig,
+    pub email: EmailConfig,
+    pub otlp: OtlpConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind_addr: SocketAddr,
+    pub cors_origins: Vec<String>,
+    pub request_timeout: Duration,
+    /// Public URL used to build links in emails
+    pub base_url: String,
+    /// Take the client IP from the last `X-Forwarded-For` entry; only enable behind a single
+    /// proxy that appends to it
+    pub trust_proxy_headers: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub url: String,
+}
+
+/// How access tokens are signed and what they claim.
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    /// One of HS256, RS256, ES256 or EdDSA
+    pub algorithm: Algorithm,
+    pub key: JwtKeyConfig,
+    /// `kid` of the signing key; defaults to the RFC 7638 thumbprint of its public key
+    pub key_id: Option<String>,
+    /// Public keys that are accepted besides the signing key and published on the JWKS endpoint,
+    /// e.g. the previous key while its tokens are still live
+    pub verification_keys: Vec<JwtVerificationKeyConfig>,
+    /// Value of the `iss` claim, checked when validating tokens
+    pub issuer: String,
+    /// Value of the `aud` claim, checked when validating tokens
+    pub audience: String,
+    pub access_token_ttl: Duration,
+    /// How often each instance picks up access tokens revoked by other instances
+    pub revocation_sync_interval: Duration,
+}
+
+/// TOTP two-factor authentication.
+#[derive(Clone)]
+pub struct MfaConfig {
+    /// AES-256 key the TOTP secrets are encrypted with in the database
+    pub encryption_key: [u8; MFA_ENCRYPTION_KEY_LEN],
+    /// Name authenticator apps show next to the account
+    pub issuer: String,
+    /// How long a login may wait for its second factor
+    pub challenge_ttl: Duration,
+}
+
+/// Identity providers users can sign in with, e.g. Google or GitHub.
+#[derive(Debug, Clone, Default)]
+pub struct OAuthConfig {
+    pub providers: Vec<OAuthProviderConfig>,
+    /// Page of the frontend users are sent back to after signing in, with a one-time code it
+    /// exchanges for the tokens; required if any provider is configured
+    pub frontend_url: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct OAuthProviderConfig {
+    /// Names the provider in its URLs, e.g. `google` in `/api/auth/oauth/google/authorize`
+    pub name: String,
+    pub kind: OAuthProviderKind,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum OAuthProviderKind {
+    /// An OpenID Connect provider, set up through discovery from its issuer URL
+    Oidc { issuer_url: String },
+    /// GitHub, which only speaks plain OAuth 2.0; the URLs can point at GitHub Enterprise instead
+    GitHub {
+        auth_url: String,
+        token_url: String,
+        api_url: String,
+    },
+}
+
+#[derive(Clone)]
+pub enum JwtKeyConfig {
+    /// Shared secret for HS256
+    Secret(String),
+    /// PEM key pair for RS256, ES256 and EdDSA; the public key can be shared with other services
+    /// so they can verify our tokens
+    Pem {
+        private_key_file: PathBuf,
+        public_key_file: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailConfig {
+    pub from_email: String,
+    pub from_name: String,
+    pub transport: EmailTransportConfig,
+    pub outbox: OutboxConfig,
+}
+
+/// How the background worker drains the email outbox.
+#[derive(Clone)]
+pub struct OutboxConfig {
+    /// AES-256 key the tokens in queued emails are encrypted with; required unless the transport
+    /// is `log` or `memory`, for which a random one is generated at startup
+    pub encryption_key: Option<[u8; OUTBOX_ENCRYPTION_KEY_LEN]>,
+    pub poll_interval: Duration,
+    pub batch_size: i64,
+    /// Messages that failed this many times are dead-lettered
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles with every further failure up to `backoff_max`
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+}
+
+/// Where outgoing emails are delivered, selected with `email.transport` / `EMAIL_TRANSPORT`.
+#[derive(Debug, Clone)]
+pub enum EmailTransportConfig {
+    /// Deliver through an SMTP relay
+    Smtp(SmtpConfig),
+    /// Write every message as an `.eml` file into a directory
+    File { dir: PathBuf },
+    /// Print every message to the log
+    Log,
+    /// Keep messages in memory, for tests
+    Memory,
+}
+
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct OtlpConfig {
+    pub endpoint: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JwtVerificationKeyConfig {
+    /// Defaults to the RFC 7638 thumbprint of the key
+    pub key_id: Option<String>,
+    /// One of RS256, ES256 or EdDSA
+    pub algorithm: Algorithm,
+    pub public_key_file: PathBuf,
+}
+
+// Secrets must never end up in logs
+impl std::fmt::Debug for JwtKeyConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JwtKeyConfig::Secret(_) => f.debug_tuple("Secret").field(&"[redacted]").finish(),
+            JwtKeyConfig::Pem {
+                private_key_file,
+                public_key_file,
+            } => f
+                .debug_struct("Pem")
+                .field("private_key_file", private_key_file)
+                .field("public_key_file", public_key_file)
+                .finish(),
+        }
+    }
+}
+
+impl std::fmt::Debug for MfaConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MfaConfig")
+            .field("encryption_key", &"[redacted]")
+            .field("issuer", &self.issuer)
+            .field("challenge_ttl", &self.challenge_ttl)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for OutboxConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutboxConfig")
+            .field("encryption_key", &self.encryption_key.map(|_| "[redacted]"))
+            .field("poll_interval", &self.poll_interval)
+            .field("batch_size", &self.batch_size)
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff_base", &self.backoff_base)
+            .field("backoff_max", &self.backoff_max)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for OAuthProviderConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuthProviderConfig")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"[redacted]")
+            .field("scopes", &self.scopes)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &"[redacted]")
+            .finish()
+    }
+}
+
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum ConfigError {
+    /// could not read config file {0}
+    Read(PathBuf, #[source] std::io::Error),
+    /// could not parse config file {0}
+    Parse(PathBuf, #[source] toml::de::Error),
+    /// missing required setting `{0}` (set it in the config file or via the {1} environment variable)
+    Missing(&'static str, &'static str),
+    /// invalid value for `{0}`: {1}
+    Invalid(&'static str, String),
+}
+
+// Mirror of the TOML file layout; every field is optional so env vars can fill the gaps
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: FileServerConfig,
+    database: FileDatabaseConfig,
+    jwt: FileJwtConfig,
+    mfa: FileMfaConfig,
+    oauth: FileOAuthConfig,
+    email: FileEmailConfig,
+    smtp: FileSmtpConfig,
+    otlp: FileOtlpConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileServerConfig {
+    bind_addr: Option<String>,
+    cors_origins: Option<Vec<String>>,
+    request_timeout_secs: Option<u64>,
+    base_url: Option<String>,
+    trust_proxy_headers: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileDatabaseConfig {
+    url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileJwtConfig {
+    algorithm: Option<String>,
+    secret: Option<String>,
+    private_key_file: Option<PathBuf>,
+    public_key_file: Option<PathBuf>,
+    key_id: Option<String>,
+    verification_keys: Option<Vec<FileJwtVerificationKeyConfig>>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    access_token_ttl_secs: Option<u64>,
+    revocation_sync_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileJwtVerificationKeyConfig {
+    key_id: Option<String>,
+    algorithm: String,
+    public_key_file: PathBuf,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileMfaConfig {
+    encryption_key: Option<String>,
+    issuer: Option<String>,
+    challenge_ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileOAuthConfig {
+    providers: Option<Vec<FileOAuthProviderConfig>>,
+    frontend_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileOAuthProviderConfig {
+    name: String,
+    kind: String,
+    client_id: String,
+    client_secret: String,
+    issuer_url: Option<String>,
+    auth_url: Option<String>,
+    token_url: Option<String>,
+    api_url: Option<String>,
+    scopes: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileEmailConfig {
+    transport: Option<String>,
+    from_email: Option<String>,
+    from_name: Option<String>,
+    dir: Option<PathBuf>,
+    outbox: FileOutboxConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileOutboxConfig {
+    encryption_key: Option<String>,
+    poll_interval_secs: Option<u64>,
+    batch_size: Option<i64>,
+    max_attempts: Option<u32>,
+    backoff_base_secs: Option<u64>,
+    backoff_max_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSmtpConfig {
+    host: Option<String>,
+    port: Option<u16>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileOtlpConfig {
+    endpoint: Option<String>,
+    token: Option<String>,
+}
+
+impl Config {
+    /// Loads and validates the configuration from the config file and the environment.
+    pub fn load() -> Result<Self, ConfigError> {
+        let file = match env::var("CONFIG_FILE") {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()),
+        };
+
+        let file_config = match file {
+            Some(path) => {
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|err| ConfigError::Read(path.clone(), err))?;
+                toml::from_str(&contents).map_err(|err| ConfigError::Parse(path, err))?
+            }
+            None => FileConfig::default(),
+        };
+
+        Self::from_sources(file_config)
+    }
+
+    fn from_sources(file: FileConfig) -> Result<Self, ConfigError> {
+        let bind_addr = env_or("BIND_ADDR", file.server.bind_addr)
+            .unwrap_or_else(|| "0.0.0.0:3000".to_string());
+        let bind_addr = bind_addr.parse().map_err(|_| {
+            ConfigError::Invalid(
+                "server.bind_addr",
+                format!("`{bind_addr}` is not a socket address"),
+            )
+        })?;
+
+        let cors_origins = match env::var("CORS_ORIGINS") {
+            Ok(origins) => origins
+                .split(',')
+                .map(|origin| origin.trim().to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect(),
+            Err(_) => file
+                .server
+                .cors_origins
+                .unwrap_or_else(|| vec!["https://example.com".to_string()]),
+        };
+        for origin in &cors_origins {
+            origin.parse::<axum::http::HeaderValue>().map_err(|_| {
+                ConfigError::Invalid(
+                    "server.cors_origins",
+                    format!("`{origin}` is not a valid origin"),
+                )
+            })?;
+        }
+
+        let request_timeout_secs = match env::var("REQUEST_TIMEOUT_SECS") {
+            Ok(secs) => secs.parse().map_err(|_| {
+                ConfigError::Invalid(
+                    "server.request_timeout_secs",
+                    format!("`{secs}` is not a number"),
+                )
+            })?,
+            Err(_) => file.server.request_timeout_secs.unwrap_or(30),
+        };
+        if request_timeout_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "server.request_timeout_secs",
+                "must be greater than zero".to_string(),
+            ));
+        }
+
+        let base_url = env_or("BASE_URL", file.server.base_url)
+            .unwrap_or_else(|| "http://localhost:3000".to_string())
+            .trim_end_matches('/')
+            .to_string();
+
+        let trust_proxy_headers = match env_or("TRUST_PROXY_HEADERS", None) {
+            Some(value) => value.parse().map_err(|_| {
+                ConfigError::Invalid(
+                    "server.trust_proxy_headers",
+                    format!("`{value}` is not true or false"),
+                )
+            })?,
+            None => file.server.trust_proxy_headers.unwrap_or(false),
+        };
+
+        let database_url = env_or("DATABASE_URL", file.database.url)
+            .ok_or(ConfigError::Missing("database.url", "DATABASE_URL"))?;
+
+        let jwt = jwt(file.jwt, &base_url)?;
+        let mfa = mfa(file.mfa)?;
+        let oauth = oauth(file.oauth)?;
+
+        let transport = email_transport(file.email.transport, file.email.dir, file.smtp)?;
+        let email = EmailConfig {
+            from_email: env_or("SMTP_FROM_EMAIL", file.email.from_email)
+                .ok_or(ConfigError::Missing("email.from_email", "SMTP_FROM_EMAIL"))?,
+            from_name: env_or("SMTP_FROM_NAME", file.email.from_name)
+                .ok_or(ConfigError::Missing("email.from_name", "SMTP_FROM_NAME"))?,
+            outbox: outbox(file.email.outbox, &transport)?,
+            transport,
+        };
+        format!("{} <{}>", email.from_name, email.from_email)
+            .parse::<lettre::message::Mailbox>()
+            .map_err(|err| ConfigError::Invalid("email.from_email", err.to_string()))?;
+
+        let otlp = OtlpConfig {
+            endpoint: env_or("OLTP_ENDPOINT", file.otlp.endpoint),
+            token: env_or("OLTP_TOKEN", file.otlp.token),
+        };
+
+        Ok(Self {
+            server: ServerConfig {
+                bind_addr,
+                cors_origins,
+                request_timeout: Duration::from_secs(request_timeout_secs),
+                base_url,
+                trust_proxy_headers,
+            },
+            database: DatabaseConfig { url: database_url },
+            jwt,
+            mfa,
+            oauth,
+            email,
+            otlp,
+        })
+    }
+}
+
+fn jwt(file: FileJwtConfig, base_url: &str) -> Result<JwtConfig, ConfigError> {
+    let algorithm = env_or("JWT_ALGORITHM", file.algorithm).unwrap_or_else(|| "HS256".to_string());
+    let algorithm = jwt_algorithm("jwt.algorithm", &algorithm, true)?;
+
+    let key = if algorithm == Algorithm::HS256 {
+        let secret = env_or("JWT_SECRET", file.secret)
+            .ok_or(ConfigError::Missing("jwt.secret", "JWT_SECRET"))?;
+        if secret.len() < MIN_JWT_SECRET_LEN {
+            return Err(ConfigError::Invalid(
+                "jwt.secret",
+                format!("must be at least {MIN_JWT_SECRET_LEN} bytes long"),
+            ));
+        }
+        JwtKeyConfig::Secret(secret)
+    } else {
+        JwtKeyConfig::Pem {
+            private_key_file: env_or("JWT_PRIVATE_KEY_FILE", None)
+                .map(PathBuf::from)
+                .or(file.private_key_file)
+                .ok_or(ConfigError::Missing(
+                    "jwt.private_key_file",
+                    "JWT_PRIVATE_KEY_FILE",
+                ))?,
+            public_key_file: env_or("JWT_PUBLIC_KEY_FILE", None)
+                .map(PathBuf::from)
+                .or(file.public_key_file)
+                .ok_or(ConfigError::Missing(
+                    "jwt.public_key_file",
+                    "JWT_PUBLIC_KEY_FILE",
+                ))?,
+        }
+    };
+
+    // Only the file can set key ids for verification keys; the variable holds `ALG:path` pairs
+    let verification_keys = match env_or("JWT_VERIFICATION_KEYS", None) {
+        Some(keys) => keys
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(|key| {
+                let (algorithm, path) = key.split_once(':').ok_or_else(|| {
+                    ConfigError::Invalid(
+                        "jwt.verification_keys",
+                        format!("`{key}` is not of the form ALG:path"),
+                    )
+                })?;
+                Ok(JwtVerificationKeyConfig {
+                    key_id: None,
+                    algorithm: jwt_algorithm("jwt.verification_keys", algorithm, false)?,
+                    public_key_file: PathBuf::from(path),
+                })
+            })
+            .collect::<Result<_, ConfigError>>()?,
+        None => file
+            .verification_keys
+            .unwrap_or_default()
+            .into_iter()
+            .map(|key| {
+                Ok(JwtVerificationKeyConfig {
+                    key_id: key.key_id,
+                    algorithm: jwt_algorithm("jwt.verification_keys", &key.algorithm, false)?,
+                    public_key_file: key.public_key_file,
+                })
+            })
+            .collect::<Result<_, ConfigError>>()?,
+    };
+
+    let access_token_ttl_secs = env_parse_or(
+        "JWT_ACCESS_TOKEN_TTL_SECS",
+        "jwt.access_token_ttl_secs",
+        file.access_token_ttl_secs,
+    )?
+    .unwrap_or(900);
+    if !(1..=MAX_ACCESS_TOKEN_TTL_SECS).contains(&access_token_ttl_secs) {
+        return Err(ConfigError::Invalid(
+            "jwt.access_token_ttl_secs",
+            format!("must be between 1 and {MAX_ACCESS_TOKEN_TTL_SECS}"),
+        ));
+    }
+
+    let revocation_sync_interval_secs = env_parse_or(
+        "JWT_REVOCATION_SYNC_INTERVAL_SECS",
+        "jwt.revocation_sync_interval_secs",
+        file.revocation_sync_interval_secs,
+    )?
+    .unwrap_or(5);
+    if revocation_sync_interval_secs == 0 {
+        return Err(ConfigError::Invalid(
+            "jwt.revocation_sync_interval_secs",
+            "must be greater than zero".to_string(),
+        ));
+    }
+
+    Ok(JwtConfig {
+        algorithm,
+        key,
+        key_id: env_or("JWT_KEY_ID", file.key_id),
+        verification_keys,
+        issuer: env_or("JWT_ISSUER", file.issuer).unwrap_or_else(|| base_url.to_string()),
+        audience: env_or("JWT_AUDIENCE", file.audience).unwrap_or_else(|| base_url.to_string()),
+        access_token_ttl: Duration::from_secs(access_token_ttl_secs),
+        revocation_sync_interval: Duration::from_secs(revocation_sync_interval_secs),
+    })
+}
+
+fn mfa(file: FileMfaConfig) -> Result<Option<MfaConfig>, ConfigError> {
+    let Some(encryption_key) = env_or("MFA_ENCRYPTION_KEY", file.encryption_key) else {
+        return Ok(None);
+    };
+    let encryption_key = decode_key(&encryption_key, "mfa.encryption_key")?;
+
+    // The issuer is part of the `otpauth://` label, where a colon separates it from the account
+    let issuer = env_or("MFA_ISSUER", file.issuer).unwrap_or_else(|| "RealWorld".to_string());
+    if issuer.is_empty() || issuer.contains(':') {
+        return Err(ConfigError::Invalid(
+            "mfa.issuer",
+            "must be non-empty and must not contain a colon".to_string(),
+        ));
+    }
+
+    let challenge_ttl_secs = env_parse_or(
+        "MFA_CHALLENGE_TTL_SECS",
+        "mfa.challenge_ttl_secs",
+        file.challenge_ttl_secs,
+    )?
+    .unwrap_or(300);
+    if challenge_ttl_secs == 0 {
+        return Err(ConfigError::Invalid(
+            "mfa.challenge_ttl_secs",
+            "must be greater than zero".to_string(),
+        ));
+    }
+
+    Ok(Some(MfaConfig {
+        encryption_key,
+        issuer,
+        challenge_ttl: Duration::from_secs(challenge_ttl_secs),
+    }))
+}
+
+fn oauth(file: FileOAuthConfig) -> Result<OAuthConfig, ConfigError> {
+    let mut providers = file
+        .providers
+        .unwrap_or_default()
+        .into_iter()
+        .map(|provider| {
+            let kind = match provider.kind.as_str() {
+                "oidc" => OAuthProviderKind::Oidc {
+                    issuer_url: provider.issuer_url.ok_or_else(|| {
+                        ConfigError::Invalid(
+                            "oauth.providers",
+                            format!("`{}` needs an issuer_url", provider.name),
+                        )
+                    })?,
+                },
+                "github" => github(provider.auth_url, provider.token_url, provider.api_url),
+                other => {
+                    return Err(ConfigError::Invalid(
+                        "oauth.providers",
+                        format!("`{other}` is not one of oidc, github"),
+                    ));
+                }
+            };
+            Ok(OAuthProviderConfig {
+                scopes: provider.scopes.unwrap_or_else(|| default_scopes(&kind)),
+                name: provider.name,
+                kind,
+                client_id: provider.client_id,
+                client_secret: provider.client_secret,
+            })
+        })
+        .collect::<Result<Vec<_>, ConfigError>>()?;
+
+    // The well-known providers can be set up from the environment alone, replacing the file's
+    let from_env = [
+        (
+            "google",
+            "GOOGLE_CLIENT_ID",
+            "GOOGLE_CLIENT_SECRET",
+            OAuthProviderKind::Oidc {
+                issuer_url: "https://accounts.google.com".to_string(),
+            },
+        ),
+        (
+            "github",
+            "GITHUB_CLIENT_ID",
+            "GITHUB_CLIENT_SECRET",
+            github(None, None, None),
+        ),
+    ];
+    for (name, id_key, secret_key, kind) in from_env {
+        let Some(client_id) = env_or(id_key, None) else {
+            continue;
+        };
+        let client_secret =
+            env_or(secret_key, None).ok_or(ConfigError::Missing("oauth.providers", secret_key))?;
+        providers.retain(|provider| provider.name != name);
+        providers.push(OAuthProviderConfig {
+            name: name.to_string(),
+            scopes: default_scopes(&kind),
+            kind,
+            client_id,
+            client_secret,
+        });
+    }
+
+    for (i, provider) in providers.iter().enumerate() {
+        if provider.name.is_empty()
+            || !provider
+                .name
+                .bytes()
+                .all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_'))
+        {
+            return Err(ConfigError::Invalid(
+                "oauth.providers",
+                format!(
+                    "`{}` is not a valid name, use lowercase letters, digits, - and _",
+                    provider.name
+                ),
+            ));
+        }
+        if providers[..i]
+            .iter()
+            .any(|other| other.name == provider.name)
+        {
+            return Err(ConfigError::Invalid(
+                "oauth.providers",
+                format!("`{}` is configured more than once", provider.name),
+            ));
+        }
+    }
+
+    let frontend_url = env_or("OAUTH_FRONTEND_URL", file.frontend_url);
+    if !providers.is_empty() && frontend_url.is_none() {
+        return Err(ConfigError::Missing(
+            "oauth.frontend_url",
+            "OAUTH_FRONTEND_URL",
+        ));
+    }
+
+    Ok(OAuthConfig {
+        providers,
+        frontend_url,
+    })
+}
+
+fn github(
+    auth_url: Option<String>,
+    token_url: Option<String>,
+    api_url: Option<String>,
+) -> OAuthProviderKind {
+    OAuthProviderKind::GitHub {
+        auth_url: auth_url
+            .unwrap_or_else(|| "https://github.com/login/oauth/authorize".to_string()),
+        token_url: token_url
+            .unwrap_or_else(|| "https://github.com/login/oauth/access_token".to_string()),
+        api_url: api_url.unwrap_or_else(|| "https://api.github.com".to_string()),
+    }
+}
+
+fn default_scopes(kind: &OAuthProviderKind) -> Vec<String> {
+    let scopes: &[&str] = match kind {
+        OAuthProviderKind::Oidc { .. } => &["openid", "email", "profile"],
+        OAuthProviderKind::GitHub { .. } => &["read:user", "user:email"],
+    };
+    scopes.iter().map(|scope| scope.to_string()).collect()
+}
+
+// HS256 keys are shared secrets, so they can only sign, never be handed out for verification
+fn jwt_algorithm(
+    setting: &'static str,
+    algorithm: &str,
+    allow_hs256: bool,
+) -> Result<Algorithm, ConfigError> {
+    match algorithm.parse() {
+        Ok(algorithm @ (Algorithm::RS256 | Algorithm::ES256 | Algorithm::EdDSA)) => Ok(algorithm),
+        Ok(Algorithm::HS256) if allow_hs256 => Ok(Algorithm::HS256),
+        _ => Err(ConfigError::Invalid(
+            setting,
+            format!(
+                "`{algorithm}` is not one of {}",
+                if allow_hs256 {
+                    "HS256, RS256, ES256, EdDSA"
+                } else {
+                    "RS256, ES256, EdDSA"
+                }
+            ),
+        )),
+    }
+}
+
+fn email_transport(
+    transport: Option<String>,
+    dir: Option<PathBuf>,
+    smtp: FileSmtpConfig,
+) -> Result<EmailTransportConfig, ConfigError> {
+    let transport = env_or("EMAIL_TRANSPORT", transport).unwrap_or_else(|| "smtp".to_string());
+
+    match transport.as_str() {
+        "smtp" => {
+            let port = match env::var("SMTP_PORT") {
+                Ok(port) => port.parse().map_err(|_| {
+                    ConfigError::Invalid("smtp.port", format!("`{port}` is not a valid port"))
+                })?,
+                Err(_) => smtp
+                    .port
+                    .ok_or(ConfigError::Missing("smtp.port", "SMTP_PORT"))?,
+            };
+
+            Ok(EmailTransportConfig::Smtp(SmtpConfig {
+                host: env_or("SMTP_HOST", smtp.host)
+                    .ok_or(ConfigError::Missing("smtp.host", "SMTP_HOST"))?,
+                port,
+                username: env_or("SMTP_USERNAME", smtp.username)
+                    .ok_or(ConfigError::Missing("smtp.username", "SMTP_USERNAME"))?,
+                password: env_or("SMTP_PASSWORD", smtp.password)
+                    .ok_or(ConfigError::Missing("smtp.password", "SMTP_PASSWORD"))?,
+            }))
+        }
+        "file" => {
+            let dir = env::var("EMAIL_DIR")
+                .ok()
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or(dir)
+                .unwrap_or_else(|| PathBuf::from("emails"));
+            Ok(EmailTransportConfig::File { dir })
+        }
+        "log" => Ok(EmailTransportConfig::Log),
+        "memory" => Ok(EmailTransportConfig::Memory),
+        other => Err(ConfigError::Invalid(
+            "email.transport",
+            format!("`{other}` is not one of smtp, file, log, memory"),
+        )),
+    }
+}
+
+fn outbox(
+    file: FileOutboxConfig,
+    transport: &EmailTransportConfig,
+) -> Result<OutboxConfig, ConfigError> {
+    let encryption_key = env_or("EMAIL_OUTBOX_ENCRYPTION_KEY", file.encryption_key)
+        .map(|key| decode_key(&key, "email.outbox.encryption_key"))
+        .transpose()?;
+    // Without a shared key, emails queued before a restart or by another instance could never be
+    // sent; only transports that don't actually deliver can do without one
+    if encryption_key.is_none()
+        && !matches!(
+            transport,
+            EmailTransportConfig::Log | EmailTransportConfig::Memory
+        )
+    {
+        return Err(ConfigError::Missing(
+            "email.outbox.encryption_key",
+            "EMAIL_OUTBOX_ENCRYPTION_KEY",
+        ));
+    }
+    let poll_interval_secs = env_parse_or(
+        "EMAIL_OUTBOX_POLL_INTERVAL_SECS",
+        "email.outbox.poll_interval_secs",
+        file.poll_interval_secs,
+    )?
+    .unwrap_or(5);
+    let batch_size = env_parse_or(
+        "EMAIL_OUTBOX_BATCH_SIZE",
+        "email.outbox.batch_size",
+        file.batch_size,
+    )?
+    .unwrap_or(20);
+    let max_attempts = env_parse_or(
+        "EMAIL_OUTBOX_MAX_ATTEMPTS",
+        "email.outbox.max_attempts",
+        file.max_attempts,
+    )?
+    .unwrap_or(8);
+    let backoff_base_secs = env_parse_or(
+        "EMAIL_OUTBOX_BACKOFF_BASE_SECS",
+        "email.outbox.backoff_base_secs",
+        file.backoff_base_secs,
+    )?
+    .unwrap_or(30);
+    let backoff_max_secs = env_parse_or(
+        "EMAIL_OUTBOX_BACKOFF_MAX_SECS",
+        "email.outbox.backoff_max_secs",
+        file.backoff_max_secs,
+    )?
+    .unwrap_or(3600);
+
+    if poll_interval_secs == 0 {
+        return Err(ConfigError::Invalid(
+            "email.outbox.poll_interval_secs",
+            "must be greater than zero".to_string(),
+        ));
+    }
+    if batch_size <= 0 {
+        return Err(ConfigError::Invalid(
+            "email.outbox.batch_size",
+            "must be greater than zero".to_string(),
+        ));
+    }
+    if max_attempts == 0 {
+        return Err(ConfigError::Invalid(
+            "email.outbox.max_attempts",
+            "must be greater than zero".to_string(),
+        ));
+    }
+    if backoff_max_secs < backoff_base_secs {
+        return Err(ConfigError::Invalid(
+            "email.outbox.backoff_max_secs",
+            "must not be less than email.outbox.backoff_base_secs".to_string(),
+        ));
+    }
+
+    Ok(OutboxConfig {
+        encryption_key,
+        poll_interval: Duration::from_secs(poll_interval_secs),
+        batch_size,
+        max_attempts,
+        backoff_base: Duration::from_secs(backoff_base_secs),
+        backoff_max: Duration::from_secs(backoff_max_secs),
+    })
+}
+
+// A base64 encoded AES-256 key
+fn decode_key<const N: usize>(value: &str, field: &'static str) -> Result<[u8; N], ConfigError> {
+    STANDARD
+        .decode(value.trim())
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .ok_or_else(|| ConfigError::Invalid(field, format!("must be {N} bytes, base64 encoded")))
+}
+
+// Environment variables take precedence over the config file
+fn env_or(key: &str, fallback: Option<String>) -> Option<String> {
+    env::var(key)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .or(fallback)
+}
+
+// Like `env_or`, for settings that have to be parsed from the environment variable
+fn env_parse_or<T: FromStr>(
+    key: &str,
+    setting: &'static str,
+    fallback: Option<T>,
+) -> Result<Option<T>, ConfigError> {
+    match env::var(key).ok().filter(|value| !value.is_empty()) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| ConfigError::Invalid(setting, format!("`{value}` is not a valid number"))),
+        None => Ok(fallback),
+    }
+}
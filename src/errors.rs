@@ -1,11 +1,20 @@
+use std::collections::BTreeMap;
+
 use askama::Template;
 use axum::{
+    Json,
     http::StatusCode,
     response::{Html, IntoResponse, Response},
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::error;
+use validator::ValidationErrors;
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize, strum::Display)]
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Deserialize, Serialize, sqlx::Type, strum::Display,
+)]
+#[sqlx(type_name = "varchar")]
 #[allow(non_camel_case_types)]
 pub enum Lang {
     #[default]
@@ -50,3 +59,130 @@ impl IntoResponse for AppError {
         }
     }
 }
+
+/// Errors returned by the JSON API handlers.
+///
+/// They are rendered in the RealWorld error format, `{"errors": {"body": ["..."]}}`. Validation
+/// errors are keyed by field instead, e.g. `{"errors": {"email": ["Invalid email format"]}}`.
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum ApiError {
+    /// validation failed
+    Validation(#[from] ValidationErrors),
+    /// {0}
+    BadRequest(&'static str),
+    /// {0}
+    Unprocessable(&'static str),
+    /// missing or invalid credentials
+    Unauthorized,
+    /// you are not allowed to perform this action
+    Forbidden,
+    /// {0} not found
+    NotFound(&'static str),
+    /// {0} has already been taken
+    Conflict(&'static str),
+    /// {0} has expired
+    Gone(&'static str),
+    /// {0} is not enabled on this server
+    Disabled(&'static str),
+    /// database error
+    Database(#[source] sqlx::Error),
+    /// could not hash or verify password
+    Password(#[from] bcrypt::BcryptError),
+    /// could not encode access token
+    Jwt(#[from] jsonwebtoken::errors::Error),
+    /// two-factor authentication failed
+    Mfa(#[from] crate::auth::mfa::MfaError),
+    /// {0}
+    OAuth(#[from] crate::auth::oauth::OAuthError),
+    /// could not queue email
+    Outbox(#[from] crate::services::outbox_cipher::OutboxCipherError),
+    /// {0}
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Validation(_) | ApiError::Unprocessable(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) | ApiError::Disabled(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Gone(_) => StatusCode::GONE,
+            ApiError::Database(_)
+            | ApiError::Password(_)
+            | ApiError::Jwt(_)
+            | ApiError::Mfa(_)
+            | ApiError::Outbox(_)
+            | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            // A refused login is the user's (or an attacker's) doing, not the provider failing
+            ApiError::OAuth(err) if err.is_rejection() => StatusCode::UNAUTHORIZED,
+            ApiError::OAuth(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        if let sqlx::Error::RowNotFound = err {
+            return ApiError::NotFound("resource");
+        }
+
+        // Unique violations surface races the handlers' own existence checks can't rule out
+        if let Some(db_err) = err.as_database_error()
+            && db_err.is_unique_violation()
+        {
+            return ApiError::Conflict(match db_err.constraint() {
+                Some("users_email_key") => "email",
+                Some("users_username_key") => "username",
+                Some("articles_slug_key") => "slug",
+                _ => "resource",
+            });
+        }
+
+        ApiError::Database(err)
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for ApiError {
+    fn from(err: Box<dyn std::error::Error>) -> Self {
+        ApiError::Internal(err.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+
+        let errors: BTreeMap<String, Vec<String>> = match &self {
+            ApiError::Validation(errors) => errors
+                .field_errors()
+                .into_iter()
+                .map(|(field, errors)| {
+                    let messages = errors
+                        .iter()
+                        .map(|error| match &error.message {
+                            Some(message) => message.to_string(),
+                            None => error.code.to_string(),
+                        })
+                        .collect();
+                    (field.to_string(), messages)
+                })
+                .collect(),
+            // Don't leak internals to clients, log them instead
+            _ if status.is_server_error() => {
+                error!("{:?}", self);
+                BTreeMap::from([(
+                    "body".to_string(),
+                    vec!["internal server error".to_string()],
+                )])
+            }
+            _ => BTreeMap::from([("body".to_string(), vec![self.to_string()])]),
+        };
+
+        (status, Json(json!({ "errors": errors }))).into_response()
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{
+    auth::middleware::{OptionalAuth, RequireAuth},
+    errors::ApiError,
+    models::{Article, ArticleView},
+    repositories::ArticleRepositoryTrait,
+    schemas::{
+        ArticleData, ArticleResponse, CreateArticleData, CreateArticleRequest, FeedQuery,
+        ListArticlesQuery, MultipleArticlesResponse, UpdateArticleRequest,
+    },
+    state::AppState,
+    utils::{slugify, unique_slug},
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+// Suffixed slugs tried after the plain one before giving up
+const MAX_SLUG_RETRIES: usize = 3;
+
+// Build a slug from the title, falling back to a suffixed slug if another article has it
+async fn generate_slug(
+    state: &AppState,
+    title: &str,
+    article_id: Option<Uuid>,
+) -> Result<String, ApiError> {
+    let slug = slugify(title);
+
+    // An article keeps its own slug, e.g. when only the case of the title changes
+    let taken = state
+        .article_repository
+        .find_by_slug(&slug)
+        .await?
+        .is_some_and(|article| Some(article.id) != article_id);
+
+    if taken {
+        Ok(unique_slug(&slug))
+    } else {
+        Ok(slug)
+    }
+}
+
+// Inserts the article under the plain slug, or a suffixed one if it is taken. The insert itself
+// detects the conflict, so a concurrent request claiming the same slug can't make it fail.
+async fn create_with_slug(
+    articles: &dyn ArticleRepositoryTrait,
+    author_id: Uuid,
+    article: &CreateArticleData,
+) -> Result<Article, ApiError> {
+    let base = slugify(&article.title);
+
+    let mut slug = base.clone();
+    for _ in 0..=MAX_SLUG_RETRIES {
+        if let Some(article) = articles
+            .create(
+                author_id,
+                &slug,
+                &article.title,
+                &article.description,
+                &article.body,
+            )
+            .await?
+        {
+            return Ok(article);
+        }
+        slug = unique_slug(&base);
+    }
+
+    Err(ApiError::Conflict("slug"))
+}
+
+pub(super) async fn find_article(state: &AppState, slug: &str) -> Result<Article, ApiError> {
+    state
+        .article_repository
+        .find_by_slug(slug)
+        .await?
+        .ok_or(ApiError::NotFound("article"))
+}
+
+async fn find_article_view(
+    state: &AppState,
+    slug: &str,
+    viewer_id: Option<Uuid>,
+) -> Result<ArticleView, ApiError> {
+    state
+        .article_repository
+        .find_view_by_slug(slug, viewer_id)
+        .await?
+        .ok_or(ApiError::NotFound("article"))
+}
+
+#[instrument(skip(state, viewer))]
+pub async fn list_articles(
+    State(state): State<AppState>,
+    OptionalAuth(viewer): OptionalAuth,
+    Query(query): Query<ListArticlesQuery>,
+) -> Result<Json<MultipleArticlesResponse>, ApiError> {
+    let (articles, articles_count) = state
+        .article_repository
+        .list(&query.into_filter(), viewer.map(|user| user.id))
+        .await?;
+
+    Ok(Json(MultipleArticlesResponse {
+        articles: articles.into_iter().map(ArticleData::from_view).collect(),
+        articles_count,
+    }))
+}
+
+#[instrument(skip(state, user))]
+pub async fn feed_articles(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Query(query): Query<FeedQuery>,
+) -> Result<Json<MultipleArticlesResponse>, ApiError> {
+    let (articles, articles_count) = state
+        .article_repository
+        .feed(user.id, query.limit(), query.offset())
+        .await?;
+
+    Ok(Json(MultipleArticlesResponse {
+        articles: articles.into_iter().map(ArticleData::from_view).collect(),
+        articles_count,
+    }))
+}
+
+#[instrument(skip(state, user, payload), fields(title = %payload.article.title))]
+pub async fn create_article(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Json(payload): Json<CreateArticleRequest>,
+) -> Result<Json<ArticleResponse>, ApiError> {
+    // Validate input data
+    payload.article.validate()?;
+
+    // The article and its tags are saved together, or not at all
+    let uow = state.unit_of_work.begin().await?;
+
+    let article = create_with_slug(uow.articles(), user.id, &payload.article).await?;
+    uow.tags()
+        .set_article_tags(article.id, &payload.article.tag_list)
+        .await?;
+
+    uow.commit().await?;
+
+    let article = find_article_view(&state, &article.slug, Some(user.id)).await?;
+
+    Ok(Json(ArticleResponse {
+        article: ArticleData::from_view(article),
+    }))
+}
+
+#[instrument(skip(state, viewer))]
+pub async fn get_article(
+    State(state): State<AppState>,
+    OptionalAuth(viewer): OptionalAuth,
+    Path(slug): Path<String>,
+) -> Result<Json<ArticleResponse>, ApiError> {
+    let article = find_article_view(&state, &slug, viewer.map(|user| user.id)).await?;
+
+    Ok(Json(ArticleResponse {
+        article: ArticleData::from_view(article),
+    }))
+}
+
+#[instrument(skip(state, user, payload))]
+pub async fn update_article(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Path(slug): Path<String>,
+    Json(payload): Json<UpdateArticleRequest>,
+) -> Result<Json<ArticleResponse>, ApiError> {
+    // Validate input data
+    payload.article.validate()?;
+
+    let article = find_article(&state, &slug).await?;
+
+    // Only the author may edit an article
+    if !article.is_authored_by(user.id) {
+        return Err(ApiError::Forbidden);
+    }
+
+    // A new title gets a new slug
+    let new_slug = match &payload.article.title {
+        Some(title) if *title != article.title => {
+            Some(generate_slug(&state, title, Some(article.id)).await?)
+        }
+        _ => None,
+    };
+
+    let uow = state.unit_of_work.begin().await?;
+
+    let article = uow
+        .articles()
+        .update(
+            article.id,
+            new_slug.as_deref(),
+            payload.article.title.as_deref(),
+            payload.article.description.as_deref(),
+            payload.article.body.as_deref(),
+        )
+        .await?
+        .ok_or(ApiError::NotFound("article"))?;
+
+    if let Some(tag_list) = &payload.article.tag_list {
+        uow.tags().set_article_tags(article.id, tag_list).await?;
+    }
+
+    uow.commit().await?;
+
+    let article = find_article_view(&state, &article.slug, Some(user.id)).await?;
+
+    Ok(Json(ArticleResponse {
+        article: ArticleData::from_view(article),
+    }))
+}
+
+#[instrument(skip(state, user))]
+pub async fn delete_article(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Path(slug): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let article = find_article(&state, &slug).await?;
+
+    // Only the author may delete an article
+    if !article.is_authored_by(user.id) {
+        return Err(ApiError::Forbidden);
+    }
+
+    state.article_repository.delete(article.id).await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[instrument(skip(state, user))]
+pub async fn favorite_article(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Path(slug): Path<String>,
+) -> Result<Json<ArticleResponse>, ApiError> {
+    let article = find_article(&state, &slug).await?;
+
+    state
+        .article_repository
+        .favorite(article.id, user.id)
+        .await?;
+
+    let article = find_article_view(&state, &slug, Some(user.id)).await?;
+
+    Ok(Json(ArticleResponse {
+        article: ArticleData::from_view(article),
+    }))
+}
+
+#[instrument(skip(state, user))]
+pub async fn unfavorite_article(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Path(slug): Path<String>,
+) -> Result<Json<ArticleResponse>, ApiError> {
+    let article = find_article(&state, &slug).await?;
+
+    state
+        .article_repository
+        .unfavorite(article.id, user.id)
+        .await?;
+
+    let article = find_article_view(&state, &slug, Some(user.id)).await?;
+
+    Ok(Json(ArticleResponse {
+        article: ArticleData::from_view(article),
+    }))
+}
@@ -1,12 +1,16 @@
 use crate::{
     auth::{
-        jwt::generate_token,
-        middleware::RequireAuth,
+        jwt::AccessToken,
+        middleware::{CurrentSession, RequireAuth},
         password::{hash_password, verify_password},
-        tokens::generate_refresh_token,
+        tokens::TokenKind,
     },
+    errors::ApiError,
+    models::{ClientInfo, OutboxEmail, User},
+    repositories::RefreshTokenRepositoryTrait,
     schemas::{
-        LogoutRequest, LogoutResponse, RefreshTokenRequest, RefreshTokenResponse,
+        LogoutRequest, LogoutResponse, MfaRequiredResponse, RefreshTokenRequest,
+        RefreshTokenResponse, UpdateCurrentUserRequest,
         auth_schemas::*,
         password_reset_schemas::{
             ForgotPasswordRequest, ForgotPasswordResponse, ResetPasswordRequest,
@@ -14,11 +18,11 @@ use crate::{
         },
     },
     state::AppState,
-    utils::generate_verification_token,
 };
-use axum::{Json, extract::State, http::StatusCode};
+use axum::{Json, extract::State};
 use chrono::{Duration, Utc};
-use tracing::{error, info, instrument};
+use tracing::{info, instrument};
+use uuid::Uuid;
 use validator::Validate;
 
 #[instrument(
@@ -27,111 +31,76 @@ use validator::Validate;
 )]
 pub async fn register(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(payload): Json<RegisterUserRequest>,
-) -> Result<Json<LoginResponse>, StatusCode> {
+) -> Result<Json<LoginResponse>, ApiError> {
     // Validate input data
-    payload.user.validate().map_err(|err| {
-        error!("Validation error: {:?}", err);
-        StatusCode::BAD_REQUEST
-    })?;
+    payload.user.validate()?;
 
     // Check if user already exists
     if state
         .user_repository
         .find_by_email(&payload.user.email)
-        .await
-        .map_err(|err| {
-            error!("Database error: {:?}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
+        .await?
         .is_some()
     {
-        return Err(StatusCode::CONFLICT);
+        return Err(ApiError::Conflict("email"));
     }
 
     if state
         .user_repository
         .find_by_username(&payload.user.username)
-        .await
-        .map_err(|err| {
-            error!("Database error: {:?}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
+        .await?
         .is_some()
     {
-        return Err(StatusCode::CONFLICT);
+        return Err(ApiError::Conflict("username"));
     }
 
     // Hash the password
-    let password_hash = hash_password(&payload.user.password).map_err(|err| {
-        error!("Password hashing error: {:?}", err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let password_hash = hash_password(&payload.user.password)?;
+
+    // The user, their tokens and the verification email are stored atomically
+    let uow = state.unit_of_work.begin().await?;
 
     // Create user in database
-    let user = state
-        .user_repository
-        .create(&payload.user.username, &payload.user.email, &password_hash)
-        .await
-        .map_err(|err| {
-            error!("Database error: {:?}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    let verification_token = generate_verification_token();
+    let user = uow
+        .users()
+        .create(
+            &payload.user.username,
+            &payload.user.email,
+            &password_hash,
+            payload.user.lang,
+        )
+        .await?;
+
+    let verification_token = TokenKind::EmailVerification.generate();
     let expires_at = Utc::now() + Duration::hours(24);
 
-    // Save token to database
-    info!("Generated token: {}", verification_token);
-    state
-        .email_verification_repository
+    // Save token and queue the verification email; the outbox worker delivers it, so an SMTP
+    // outage doesn't fail the registration
+    uow.email_verifications()
         .create_token(user.id, &verification_token, expires_at)
-        .await
-        .map_err(|err| {
-            error!("Failed to create token in DB: {:?}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    info!("Token saved to database");
-
-    // Send verification email
-    info!("Attempting to send email...");
-    state
-        .email_service
-        .send_verification_email(&user.email, &user.username, &verification_token)
-        .await
-        .map_err(|e| {
-            error!("Failed to send verification email: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    info!("Email sent successfully");
-
-    // Generate JWT token
-    let jwt_secret = std::env::var("JWT_SECRET").map_err(|err| {
-        error!("JWT secret not found: {}", err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    let access_token = generate_token(&user.id, &jwt_secret).map_err(|err| {
-        error!("Failed to generate access token: {}", err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    // Generate refresh token (UUID, no expiration)
-    let refresh_token = generate_refresh_token();
-
-    // Save refresh token to database
-    state
-        .refresh_token_repository
-        .create_token(user.id, &refresh_token)
-        .await
-        .map_err(|err| {
-            error!("Failed to save refresh token to database: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .await?;
+    uow.email_outbox()
+        .enqueue(
+            &user.email,
+            &OutboxEmail::Verification {
+                username: user.username.clone(),
+                token: state.outbox_cipher.seal(&user.email, &verification_token)?,
+                lang: user.lang,
+            },
+        )
+        .await?;
+
+    let (access_token, refresh_token) =
+        start_session(&state, uow.refresh_tokens(), user.id, &client).await?;
+
+    uow.commit().await?;
 
     // Build response with BOTH tokens
     let response = LoginResponse {
         user: UserData::from_user(user),
-        access_token,
+        access_token: access_token.token,
         refresh_token,
     };
 
@@ -140,76 +109,108 @@ pub async fn register(
     Ok(Json(response))
 }
 
-#[instrument(skip(state))]
+#[instrument(skip(state, payload), fields(email = %payload.user.email))]
 pub async fn login(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(payload): Json<LoginUserRequest>,
-) -> Result<Json<LoginResponse>, StatusCode> {
+) -> Result<Json<LoginResult>, ApiError> {
     // Validate input
-    payload.user.validate().map_err(|err| {
-        error!("Invalid login request: {}", err);
-        StatusCode::BAD_REQUEST
-    })?;
+    payload.user.validate()?;
 
     // Find user by email
     let user = state
         .user_repository
         .find_by_email(&payload.user.email)
-        .await
-        .map_err(|err| {
-            error!("Failed to find user by email: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
 
     // Verify password
-    let password_valid =
-        verify_password(&payload.user.password, &user.password_hash).map_err(|err| {
-            error!("Failed to verify password: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    if !password_valid {
-        return Err(StatusCode::UNAUTHORIZED);
+    if !verify_password(&payload.user.password, &user.password_hash)? {
+        return Err(ApiError::Unauthorized);
     }
 
-    // Generate JWT token
-    let jwt_secret = std::env::var("JWT_SECRET").map_err(|err| {
-        error!("Failed to get JWT secret: {}", err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    let access_token = generate_token(&user.id, &jwt_secret).map_err(|err| {
-        error!("Failed to generate access token: {}", err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    Ok(Json(complete_login(&state, user, &client).await?))
+}
 
-    // Generate refresh token (UUID, no expiration)
-    let refresh_token = generate_refresh_token();
+/// Logs in a user whose password (or external identity) checked out: issues the tokens, or
+/// a challenge for the second factor if the user has two-factor authentication enabled.
+pub(crate) async fn complete_login(
+    state: &AppState,
+    user: User,
+    client: &ClientInfo,
+) -> Result<LoginResult, ApiError> {
+    if state
+        .mfa_repository
+        .find_totp(user.id)
+        .await?
+        .is_some_and(|totp| totp.is_enabled())
+    {
+        // The second factor can't be checked once the key is gone, so the login can't go on
+        let mfa = state
+            .config
+            .mfa
+            .as_ref()
+            .ok_or(ApiError::Disabled("two-factor authentication"))?;
+        let mfa_token = TokenKind::MfaChallenge.generate();
+        let expires_at = Utc::now() + Duration::seconds(mfa.challenge_ttl.as_secs() as i64);
+        state
+            .mfa_repository
+            .create_challenge(user.id, &mfa_token, expires_at)
+            .await?;
+
+        return Ok(LoginResult::MfaRequired(MfaRequiredResponse {
+            mfa_required: true,
+            mfa_token,
+            expires_at,
+        }));
+    }
 
-    // Save refresh token to database
-    state
-        .refresh_token_repository
-        .create_token(user.id, &refresh_token)
-        .await
-        .map_err(|err| {
-            error!("Failed to save refresh token: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let (access_token, refresh_token) = start_session(
+        state,
+        state.refresh_token_repository.as_ref(),
+        user.id,
+        client,
+    )
+    .await?;
 
     // Build response with BOTH tokens
-    let response = LoginResponse {
+    Ok(LoginResult::Authenticated(LoginResponse {
         user: UserData::from_user(user),
-        access_token,
+        access_token: access_token.token,
         refresh_token,
-    };
+    }))
+}
 
-    Ok(Json(response))
+/// Issues the access token and the first refresh token of a new session.
+pub(crate) async fn start_session(
+    state: &AppState,
+    refresh_tokens: &dyn RefreshTokenRepositoryTrait,
+    user_id: Uuid,
+    client: &ClientInfo,
+) -> Result<(AccessToken, String), ApiError> {
+    // Generate JWT token
+    let access_token = state.jwt.generate_token(&user_id)?;
+
+    // Generate refresh token (UUID, no expiration)
+    let refresh_token = TokenKind::Refresh.generate();
+
+    // Save refresh token to database
+    refresh_tokens
+        .create_token(
+            user_id,
+            &refresh_token,
+            access_token.jti,
+            access_token.expires_at,
+            client,
+        )
+        .await?;
+
+    Ok((access_token, refresh_token))
 }
 
-#[instrument]
-pub async fn current_user(
-    RequireAuth(user): RequireAuth,
-) -> Result<Json<UserResponse>, StatusCode> {
+#[instrument(skip(user))]
+pub async fn current_user(RequireAuth(user): RequireAuth) -> Result<Json<UserResponse>, ApiError> {
     // Build response
     let response = UserResponse {
         user: UserData::from_user(user),
@@ -218,24 +219,143 @@ pub async fn current_user(
     Ok(Json(response))
 }
 
-#[instrument(skip(state))]
+#[instrument(skip(state, session, payload), fields(user_id = %session.user.id))]
+pub async fn update_user(
+    State(state): State<AppState>,
+    session: CurrentSession,
+    Json(payload): Json<UpdateCurrentUserRequest>,
+) -> Result<Json<UserResponse>, ApiError> {
+    let user = session.user;
+    let changes = payload.user;
+
+    // Validate input data
+    changes.validate()?;
+
+    // Only treat the email as changed if it actually differs
+    let new_email = changes
+        .email
+        .as_deref()
+        .filter(|email| *email != user.email);
+
+    // Reject email/username already taken by someone else
+    if let Some(email) = new_email {
+        let existing = state.user_repository.find_by_email(email).await?;
+        if existing.is_some_and(|existing| existing.id != user.id) {
+            return Err(ApiError::Conflict("email"));
+        }
+    }
+
+    if let Some(username) = changes
+        .username
+        .as_deref()
+        .filter(|username| *username != user.username)
+    {
+        let existing = state.user_repository.find_by_username(username).await?;
+        if existing.is_some_and(|existing| existing.id != user.id) {
+            return Err(ApiError::Conflict("username"));
+        }
+    }
+
+    // Changing the password requires confirming the current one
+    let new_password_hash = match &changes.password {
+        Some(password) => {
+            let Some(current_password) = changes.current_password.as_deref() else {
+                return Err(ApiError::Unprocessable(
+                    "current password is required to change the password",
+                ));
+            };
+
+            if !verify_password(current_password, &user.password_hash)? {
+                return Err(ApiError::Forbidden);
+            }
+
+            Some(hash_password(password)?)
+        }
+        None => None,
+    };
+
+    let uow = state.unit_of_work.begin().await?;
+
+    // A concurrent request may still claim the email/username in the meantime,
+    // in which case the unique violation is reported as a conflict
+    let updated_user = uow
+        .users()
+        .update(
+            user.id,
+            changes.username.as_deref(),
+            changes.email.as_deref(),
+            changes.bio.as_ref().map(|bio| bio.as_deref()),
+            changes.image.as_ref().map(|image| image.as_deref()),
+            changes.lang,
+        )
+        .await?
+        .ok_or(ApiError::NotFound("user"))?;
+
+    // Whoever may have stolen a session or the old password shouldn't stay logged in, so every
+    // session but the one making the change ends with the old password
+    let mut revoked = Vec::new();
+    if let Some(password_hash) = new_password_hash {
+        uow.users().update_password(user.id, &password_hash).await?;
+        revoked = uow
+            .refresh_tokens()
+            .revoke_other_sessions(user.id, session.session_id)
+            .await?;
+    }
+
+    // A new email address has to be verified again
+    if new_email.is_some() {
+        let verification_token = TokenKind::EmailVerification.generate();
+        let expires_at = Utc::now() + Duration::hours(24);
+
+        uow.email_verifications()
+            .create_token(updated_user.id, &verification_token, expires_at)
+            .await?;
+        uow.email_outbox()
+            .enqueue(
+                &updated_user.email,
+                &OutboxEmail::Verification {
+                    username: updated_user.username.clone(),
+                    token: state
+                        .outbox_cipher
+                        .seal(&updated_user.email, &verification_token)?,
+                    lang: updated_user.lang,
+                },
+            )
+            .await?;
+    }
+
+    uow.commit().await?;
+
+    state
+        .access_token_denylist
+        .revoke_issued_with(&revoked)
+        .await?;
+
+    Ok(Json(UserResponse {
+        user: UserData::from_user(updated_user),
+    }))
+}
+
+#[instrument(skip(state, params))]
 pub async fn verify_email(
     State(state): State<AppState>,
     axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     // Extract token from query params
-    let token = params.get("token").ok_or(StatusCode::BAD_REQUEST)?;
+    let token = params
+        .get("token")
+        .ok_or(ApiError::BadRequest("missing token"))?;
+
+    if !TokenKind::EmailVerification.is_well_formed(token) {
+        return Err(ApiError::BadRequest("malformed token"));
+    }
 
     // Look up the token in database
     let verification_token = state
         .email_verification_repository
         .find_by_token(token)
-        .await
-        .map_err(|err| {
-            error!("Failed to find email verification token: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .await?
+        .ok_or(ApiError::NotFound("verification token"))?;
 
     // Check if expired
     if verification_token.is_expired() {
@@ -243,34 +363,22 @@ pub async fn verify_email(
         state
             .email_verification_repository
             .delete_token(token)
-            .await
-            .map_err(|err| {
-                error!("Failed to delete expired email verification token: {}", err);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
+            .await?;
 
-        return Err(StatusCode::GONE);
+        return Err(ApiError::Gone("verification token"));
     }
 
+    let uow = state.unit_of_work.begin().await?;
+
     // Mark user as verified
-    state
-        .email_verification_repository
+    uow.email_verifications()
         .verify_user_email(verification_token.user_id)
-        .await
-        .map_err(|err| {
-            error!("Failed to verify user email: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .await?;
 
     // Delete token (single-use)
-    state
-        .email_verification_repository
-        .delete_token(token)
-        .await
-        .map_err(|err| {
-            error!("Failed to delete email verification token: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    uow.email_verifications().delete_token(token).await?;
+
+    uow.commit().await?;
 
     Ok(Json(serde_json::json!({
         "message": "Email verified successfully!"
@@ -278,57 +386,47 @@ pub async fn verify_email(
 }
 
 // Handler for "Forgot Password" - generates and emails reset token
-#[instrument(skip(state))]
+#[instrument(skip(state, payload), fields(email = %payload.email))]
 pub async fn forgot_password(
     State(state): State<AppState>,
     Json(payload): Json<ForgotPasswordRequest>,
-) -> Result<Json<ForgotPasswordResponse>, StatusCode> {
+) -> Result<Json<ForgotPasswordResponse>, ApiError> {
     // Validate email format
-    payload.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+    payload.validate()?;
 
     // Look up user by email
-    let user = state
-        .user_repository
-        .find_by_email(&payload.email)
-        .await
-        .map_err(|err| {
-            error!("Failed to find user by email: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let user = state.user_repository.find_by_email(&payload.email).await?;
 
     // SECURITY: Always return success even if email doesn't exist
     // This prevents attackers from discovering which emails are registered
-    if user.is_none() {
+    let Some(user) = user else {
         return Ok(Json(ForgotPasswordResponse {
             message: "If that email exists, a password reset link has been sent.".to_string(),
         }));
-    }
-
-    let user = user.unwrap();
+    };
 
     // Generate reset token
-    let reset_token = generate_verification_token();
+    let reset_token = TokenKind::PasswordReset.generate();
     let expires_at = Utc::now() + Duration::hours(1); // 1 hour expiration
 
-    // Save token to database
-    state
-        .password_reset_repository
-        .create_token(user.id, &reset_token, expires_at)
-        .await
-        .map_err(|err| {
-            error!("Failed to create password reset token: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    // Save token and queue the reset email
+    let uow = state.unit_of_work.begin().await?;
 
-    // Send reset email
-    state
-        .email_service
-        .send_password_reset_email(&user.email, &user.username, &reset_token)
-        .await
-        .map_err(|err| {
-            error!("Failed to send password reset email: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    uow.password_resets()
+        .create_token(user.id, &reset_token, expires_at)
+        .await?;
+    uow.email_outbox()
+        .enqueue(
+            &user.email,
+            &OutboxEmail::PasswordReset {
+                username: user.username,
+                token: state.outbox_cipher.seal(&user.email, &reset_token)?,
+                lang: user.lang,
+            },
+        )
+        .await?;
+
+    uow.commit().await?;
 
     Ok(Json(ForgotPasswordResponse {
         message: "If that email exists, a password reset link has been sent.".to_string(),
@@ -336,68 +434,68 @@ pub async fn forgot_password(
 }
 
 // Handler for actually resetting the password
-#[instrument(skip(state))]
+#[instrument(skip(state, payload))]
 pub async fn reset_password(
     State(state): State<AppState>,
     Json(payload): Json<ResetPasswordRequest>,
-) -> Result<Json<ResetPasswordResponse>, StatusCode> {
+) -> Result<Json<ResetPasswordResponse>, ApiError> {
     // Validate new password
-    payload.validate().map_err(|err| {
-        error!("Failed to validate password reset request: {}", err);
-        StatusCode::BAD_REQUEST
-    })?;
-
-    // Look up token
-    let reset_token = state
-        .password_reset_repository
-        .find_by_token(&payload.token)
-        .await
-        .map_err(|err| {
-            error!("Failed to find password reset token: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .ok_or(StatusCode::NOT_FOUND)?;
-
-    // Check expiration
-    if reset_token.is_expired() {
+    payload.validate()?;
+
+    if !TokenKind::PasswordReset.is_well_formed(&payload.token) {
+        return Err(ApiError::BadRequest("malformed token"));
+    }
+
+    // Using up the token and setting the new password go together, so a token can only ever
+    // set one password
+    let uow = state.unit_of_work.begin().await?;
+
+    let Some(user_id) = uow.password_resets().consume_token(&payload.token).await? else {
+        drop(uow);
+
+        // Tell an expired link from one that was used already or never existed
+        if state
+            .password_reset_repository
+            .find_by_token(&payload.token)
+            .await?
+            .is_none()
+        {
+            return Err(ApiError::NotFound("reset token"));
+        }
         // Clean up expired token
         state
             .password_reset_repository
             .delete_token(&payload.token)
-            .await
-            .map_err(|err| {
-                error!("Failed to delete expired password reset token: {}", err);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
+            .await?;
 
-        return Err(StatusCode::GONE);
-    }
+        return Err(ApiError::Gone("reset token"));
+    };
 
     // Hash new password
-    let new_password_hash = hash_password(&payload.new_password).map_err(|err| {
-        error!("Failed to hash new password: {}", err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let new_password_hash = hash_password(&payload.new_password)?;
 
     // Update user password
-    state
-        .user_repository
-        .update_password(reset_token.user_id, &new_password_hash)
-        .await
-        .map_err(|err| {
-            error!("Failed to update user password: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    uow.users()
+        .update_password(user_id, &new_password_hash)
+        .await?;
 
     // Delete ALL reset tokens for this user (invalidate any other pending requests)
+    uow.password_resets()
+        .delete_all_user_tokens(user_id)
+        .await?;
+
+    // The password may have been reset because it leaked, so every session ends with it
+    let revoked = uow
+        .refresh_tokens()
+        .revoke_other_sessions(user_id, None)
+        .await?;
+
+    uow.commit().await?;
+
     state
-        .password_reset_repository
-        .delete_all_user_tokens(reset_token.user_id)
-        .await
-        .map_err(|err| {
-            error!("Failed to delete all user tokens: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .access_token_denylist
+        .revoke_issued_with(&revoked)
+        .await?;
 
     Ok(Json(ResetPasswordResponse {
         message: "Password has been reset successfully. You can now login with your new password."
@@ -405,23 +503,59 @@ pub async fn reset_password(
     }))
 }
 
-#[instrument(skip(state))]
+#[instrument(skip(state, payload))]
 pub async fn refresh_token(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(payload): Json<RefreshTokenRequest>,
-) -> Result<Json<RefreshTokenResponse>, StatusCode> {
-    // Step 1: Find the refresh token in database
+) -> Result<Json<RefreshTokenResponse>, ApiError> {
+    if !TokenKind::Refresh.is_well_formed(&payload.refresh_token) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    // Step 1: Consume the token. This is a single conditional UPDATE, so of several concurrent
+    // requests with the same token exactly one gets it back.
+    let uow = state.unit_of_work.begin().await?;
+
+    if let Some(consumed) = uow
+        .refresh_tokens()
+        .consume_token(&payload.refresh_token)
+        .await?
+    {
+        // Step 2: Generate new access token
+        let access_token = state.jwt.generate_token(&consumed.user_id)?;
+
+        // Step 3: Generate NEW refresh token with rotation, in the same transaction so a crash
+        // in between can't lock the user out
+        let new_refresh_token = TokenKind::Refresh.generate();
+
+        uow.refresh_tokens()
+            .create_rotated_token(
+                &consumed,
+                &new_refresh_token,
+                access_token.jti,
+                access_token.expires_at,
+                &client,
+            )
+            .await?;
+
+        uow.commit().await?;
+
+        // Step 4: Return BOTH tokens
+        return Ok(Json(RefreshTokenResponse {
+            access_token: access_token.token,
+            refresh_token: new_refresh_token,
+        }));
+    }
+    drop(uow);
+
+    // The token is unknown, expired or already used; find out which
     let refresh_token = state
         .refresh_token_repository
         .find_by_token(&payload.refresh_token)
-        .await
-        .map_err(|err| {
-            error!("Failed to find refresh token: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    // Step 2: Check if token has expired
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+
     if refresh_token.is_expired() {
         // Token is expired, delete it and reject
         let _ = state
@@ -429,104 +563,87 @@ pub async fn refresh_token(
             .delete_token(&payload.refresh_token)
             .await;
 
-        return Err(StatusCode::UNAUTHORIZED);
+        return Err(ApiError::Unauthorized);
     }
 
-    // Step 3: REUSE DETECTION - Check if token was already used
+    // REUSE DETECTION - the token was already used, either by an earlier request or by a
+    // concurrent one that won the race above
     if refresh_token.is_used {
         // SECURITY BREACH DETECTED!
         // Someone is trying to use an old token
         // This means the token was likely stolen
 
         info!("TOKEN REUSE DETECTED!");
-        info!("Token: {}", &payload.refresh_token);
         info!("User ID: {}", refresh_token.user_id);
+        info!("Family ID: {}", refresh_token.family_id);
         info!("Originally used at: {:?}", refresh_token.used_at);
 
-        // Nuclear option: Delete ALL user's refresh tokens
-        // Force them to login again
-        state
-            .refresh_token_repository
-            .delete_all_user_tokens(refresh_token.user_id)
-            .await
-            .map_err(|err| {
-                error!("Failed to delete all user tokens: {}", err);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
+        let uow = state.unit_of_work.begin().await?;
+
+        // Revoke the whole rotation family the token belongs to, whoever holds its latest token
+        // has to login again. The user's other sessions are not affected.
+        let revoked = uow
+            .refresh_tokens()
+            .revoke_family(refresh_token.family_id)
+            .await?;
+        let session_started_at = revoked.iter().map(|token| token.created_at).min();
+        // The latest token shows where the session was used last, as the session list does
+        let last_used = revoked.iter().max_by_key(|token| token.created_at);
 
         // Get user info for email
-        let user = state
-            .user_repository
+        let user = uow
+            .users()
             .find_by_id(refresh_token.user_id)
-            .await
-            .map_err(|err| {
-                error!("Failed to find user: {}", err);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?
-            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
-
-        // Send security alert email
-        if let Err(e) = state
-            .email_service
-            .send_security_alert(&user.email, &user.username)
-            .await
-        {
-            error!("Failed to send security alert email: {}", e);
-            // Don't fail the request if email fails
-        }
-
-        return Err(StatusCode::UNAUTHORIZED);
+            .await?
+            .ok_or(ApiError::NotFound("user"))?;
+
+        // Queue security alert email
+        uow.email_outbox()
+            .enqueue(
+                &user.email,
+                &OutboxEmail::SecurityAlert {
+                    username: user.username,
+                    lang: user.lang,
+                    session_started_at,
+                    user_agent: last_used.and_then(|token| token.user_agent.clone()),
+                    ip_address: last_used.and_then(|token| token.ip_address.clone()),
+                },
+            )
+            .await?;
+
+        uow.commit().await?;
+
+        // Access tokens of the family stay valid until they expire unless they are revoked too
+        state
+            .access_token_denylist
+            .revoke_issued_with(&revoked)
+            .await?;
     }
 
-    // Step 4: Mark the old token as used (consumed)
-    state
-        .refresh_token_repository
-        .mark_token_as_used(&payload.refresh_token)
-        .await
-        .map_err(|err| {
-            error!("Failed to mark token as used: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    // Step 5: Generate NEW refresh token with rotation
-    let new_refresh_token = generate_refresh_token();
-
-    state
-        .refresh_token_repository
-        .create_token(refresh_token.user_id, &new_refresh_token)
-        .await
-        .map_err(|err| {
-            error!("Failed to create new token: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    // Step 6: Generate new access token
-    let jwt_secret = std::env::var("JWT_SECRET").map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let access_token = generate_token(&refresh_token.user_id, &jwt_secret).map_err(|err| {
-        error!("Failed to generate access token: {}", err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    // Step 7: Return BOTH tokens
-    Ok(Json(RefreshTokenResponse {
-        access_token,
-        refresh_token: new_refresh_token,
-    }))
+    Err(ApiError::Unauthorized)
 }
 
-#[instrument(skip(state))]
+#[instrument(skip(state, payload))]
 pub async fn logout(
     State(state): State<AppState>,
     Json(payload): Json<LogoutRequest>,
-) -> Result<Json<LogoutResponse>, StatusCode> {
-    state
-        .refresh_token_repository
-        .delete_token(&payload.refresh_token)
-        .await
-        .map_err(|err| {
-            error!("Failed to delete token: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+) -> Result<Json<LogoutResponse>, ApiError> {
+    // End the whole session, so earlier tokens of it can't be replayed either
+    if TokenKind::Refresh.is_well_formed(&payload.refresh_token)
+        && let Some(refresh_token) = state
+            .refresh_token_repository
+            .find_by_token(&payload.refresh_token)
+            .await?
+    {
+        let revoked = state
+            .refresh_token_repository
+            .revoke_family(refresh_token.family_id)
+            .await?;
+        state
+            .access_token_denylist
+            .revoke_issued_with(&revoked)
+            .await?;
+    }
 
     Ok(Json(LogoutResponse {
         message: "Logged out successfully".to_string(),
@@ -0,0 +1,80 @@
+use super::articles::find_article;
+use crate::{
+    auth::middleware::{OptionalAuth, RequireAuth},
+    errors::ApiError,
+    schemas::{CommentData, CommentResponse, CreateCommentRequest, MultipleCommentsResponse},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+#[instrument(skip(state, user, payload))]
+pub async fn add_comment(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Path(slug): Path<String>,
+    Json(payload): Json<CreateCommentRequest>,
+) -> Result<Json<CommentResponse>, ApiError> {
+    // Validate input data
+    payload.comment.validate()?;
+
+    let article = find_article(&state, &slug).await?;
+
+    let comment = state
+        .comment_repository
+        .create(article.id, user.id, &payload.comment.body)
+        .await?;
+
+    Ok(Json(CommentResponse {
+        comment: CommentData::from_comment(comment, user),
+    }))
+}
+
+#[instrument(skip(state, viewer))]
+pub async fn list_comments(
+    State(state): State<AppState>,
+    OptionalAuth(viewer): OptionalAuth,
+    Path(slug): Path<String>,
+) -> Result<Json<MultipleCommentsResponse>, ApiError> {
+    let article = find_article(&state, &slug).await?;
+
+    let comments = state
+        .comment_repository
+        .list_for_article(article.id, viewer.map(|user| user.id))
+        .await?;
+
+    Ok(Json(MultipleCommentsResponse {
+        comments: comments.into_iter().map(CommentData::from_view).collect(),
+    }))
+}
+
+#[instrument(skip(state, user))]
+pub async fn delete_comment(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Path((slug, id)): Path<(String, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+    let article = find_article(&state, &slug).await?;
+
+    let comment = state
+        .comment_repository
+        .find_by_id(id)
+        .await?
+        .filter(|comment| comment.article_id == article.id)
+        .ok_or(ApiError::NotFound("comment"))?;
+
+    // Only the comment's author may delete it
+    if !comment.is_authored_by(user.id) {
+        return Err(ApiError::Forbidden);
+    }
+
+    state.comment_repository.delete(comment.id).await?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,18 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Json},
+};
+use tracing::instrument;
+
+use crate::state::AppState;
+
+// Gateways verifying our access tokens fetch the public keys from here; letting them cache the
+// set for a while is fine as long as new keys are published before they start signing
+#[instrument(skip(state))]
+pub async fn jwks(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(state.jwt.jwks().clone()),
+    )
+}
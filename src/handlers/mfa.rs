@@ -0,0 +1,265 @@
+use axum::{Json, extract::State};
+use chrono::Utc;
+use tracing::{info, instrument};
+
+use super::auth::start_session;
+use crate::{
+    auth::{
+        mfa::{MfaService, generate_recovery_codes},
+        middleware::RequireAuth,
+        password::verify_password,
+        tokens::TokenKind,
+    },
+    errors::ApiError,
+    models::{ClientInfo, User, UserTotp},
+    repositories::MfaRepositoryTrait,
+    schemas::{
+        DisableMfaRequest, DisableMfaResponse, EnrollMfaRequest, LoginResponse, MfaCodeRequest,
+        MfaEnrollmentResponse, MfaStatusResponse, RecoveryCodesResponse, UserData,
+        VerifyMfaLoginRequest,
+    },
+    state::AppState,
+};
+
+// Codes that can be tried against a login challenge before it has to be started over
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+#[instrument(skip(state, payload))]
+pub async fn verify_mfa_login(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(payload): Json<VerifyMfaLoginRequest>,
+) -> Result<Json<LoginResponse>, ApiError> {
+    let mfa = mfa_service(&state)?;
+    if !TokenKind::MfaChallenge.is_well_formed(&payload.mfa_token) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    // The attempt is counted in a statement of its own before the code is checked, so parallel
+    // requests can't get more guesses than that between them
+    let challenge = state
+        .mfa_repository
+        .record_attempt(&payload.mfa_token, MAX_CHALLENGE_ATTEMPTS)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+
+    // Using the code, the challenge and the new session go together, so neither the challenge
+    // nor the code can complete two logins
+    let uow = state.unit_of_work.begin().await?;
+
+    let user = uow
+        .users()
+        .find_by_id(challenge.user_id)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+    // Two-factor authentication may have been disabled since the password was checked
+    let totp = uow
+        .mfa()
+        .find_totp(user.id)
+        .await?
+        .filter(UserTotp::is_enabled)
+        .ok_or(ApiError::Unauthorized)?;
+
+    if !verify_second_factor(mfa, uow.mfa(), &totp, &user, &payload.code).await? {
+        return Err(ApiError::Unauthorized);
+    }
+    // Only a correct code uses the challenge up
+    uow.mfa()
+        .consume_challenge(&payload.mfa_token)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+
+    let (access_token, refresh_token) =
+        start_session(&state, uow.refresh_tokens(), user.id, &client).await?;
+
+    uow.commit().await?;
+
+    Ok(Json(LoginResponse {
+        user: UserData::from_user(user),
+        access_token: access_token.token,
+        refresh_token,
+    }))
+}
+
+#[instrument(skip(state, user))]
+pub async fn mfa_status(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+) -> Result<Json<MfaStatusResponse>, ApiError> {
+    mfa_service(&state)?;
+
+    let enabled = state
+        .mfa_repository
+        .find_totp(user.id)
+        .await?
+        .is_some_and(|totp| totp.is_enabled());
+    let recovery_codes_remaining = state
+        .mfa_repository
+        .count_unused_recovery_codes(user.id)
+        .await?;
+
+    Ok(Json(MfaStatusResponse {
+        enabled,
+        recovery_codes_remaining,
+    }))
+}
+
+// Starts enrollment with a new secret; it only takes effect once confirmed with a first code
+#[instrument(skip(state, user, payload))]
+pub async fn enroll_mfa(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Json(payload): Json<EnrollMfaRequest>,
+) -> Result<Json<MfaEnrollmentResponse>, ApiError> {
+    let mfa = mfa_service(&state)?;
+    if !verify_password(&payload.password, &user.password_hash)? {
+        return Err(ApiError::Forbidden);
+    }
+
+    let secret = mfa.generate_secret();
+    let (otpauth_uri, secret_base32) = mfa.provisioning(&secret, &user.email)?;
+    let secret_encrypted = mfa.encrypt_secret(user.id, &secret)?;
+
+    state
+        .mfa_repository
+        .start_enrollment(user.id, &secret_encrypted)
+        .await?
+        .ok_or(ApiError::BadRequest(
+            "two-factor authentication is already enabled",
+        ))?;
+
+    Ok(Json(MfaEnrollmentResponse {
+        secret: secret_base32,
+        otpauth_uri,
+    }))
+}
+
+#[instrument(skip(state, user, payload))]
+pub async fn confirm_mfa(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, ApiError> {
+    let mfa = mfa_service(&state)?;
+    let totp = state
+        .mfa_repository
+        .find_totp(user.id)
+        .await?
+        .filter(|totp| !totp.is_enabled())
+        .ok_or(ApiError::BadRequest("no two-factor enrollment is pending"))?;
+
+    let secret = mfa.decrypt_secret(user.id, &totp.secret_encrypted)?;
+    let step = mfa
+        .verify_code(&secret, &user.email, &payload.code, Utc::now())?
+        .ok_or(ApiError::BadRequest("invalid code"))?;
+
+    let recovery_codes = generate_recovery_codes();
+
+    let uow = state.unit_of_work.begin().await?;
+
+    // Another request may have confirmed the enrollment in the meantime
+    if !uow.mfa().enable_totp(user.id, step).await? {
+        return Err(ApiError::BadRequest("no two-factor enrollment is pending"));
+    }
+    uow.mfa()
+        .replace_recovery_codes(user.id, &recovery_codes)
+        .await?;
+
+    uow.commit().await?;
+
+    info!("Two-factor authentication enabled");
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[instrument(skip(state, user, payload))]
+pub async fn disable_mfa(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Json(payload): Json<DisableMfaRequest>,
+) -> Result<Json<DisableMfaResponse>, ApiError> {
+    let mfa = mfa_service(&state)?;
+    if !verify_password(&payload.password, &user.password_hash)? {
+        return Err(ApiError::Forbidden);
+    }
+
+    let uow = state.unit_of_work.begin().await?;
+
+    let totp = uow
+        .mfa()
+        .find_totp(user.id)
+        .await?
+        .filter(UserTotp::is_enabled)
+        .ok_or(ApiError::BadRequest(
+            "two-factor authentication is not enabled",
+        ))?;
+    if !verify_second_factor(mfa, uow.mfa(), &totp, &user, &payload.code).await? {
+        return Err(ApiError::BadRequest("invalid code"));
+    }
+
+    uow.mfa().delete_totp(user.id).await?;
+    uow.mfa().delete_user_challenges(user.id).await?;
+
+    uow.commit().await?;
+
+    info!("Two-factor authentication disabled");
+
+    Ok(Json(DisableMfaResponse {
+        message: "Two-factor authentication has been disabled".to_string(),
+    }))
+}
+
+// Replaces all recovery codes, e.g. when they ran low or may have leaked
+#[instrument(skip(state, user, payload))]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, ApiError> {
+    let mfa = mfa_service(&state)?;
+    let uow = state.unit_of_work.begin().await?;
+
+    let totp = uow
+        .mfa()
+        .find_totp(user.id)
+        .await?
+        .filter(UserTotp::is_enabled)
+        .ok_or(ApiError::BadRequest(
+            "two-factor authentication is not enabled",
+        ))?;
+    if !verify_second_factor(mfa, uow.mfa(), &totp, &user, &payload.code).await? {
+        return Err(ApiError::BadRequest("invalid code"));
+    }
+
+    let recovery_codes = generate_recovery_codes();
+    uow.mfa()
+        .replace_recovery_codes(user.id, &recovery_codes)
+        .await?;
+
+    uow.commit().await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+// Checks an authenticator code or, failing that, a recovery code, and uses it up
+async fn verify_second_factor(
+    mfa: &MfaService,
+    repository: &dyn MfaRepositoryTrait,
+    totp: &UserTotp,
+    user: &User,
+    code: &str,
+) -> Result<bool, ApiError> {
+    let secret = mfa.decrypt_secret(user.id, &totp.secret_encrypted)?;
+    if let Some(step) = mfa.verify_code(&secret, &user.email, code, Utc::now())? {
+        return Ok(repository.use_totp_step(user.id, step).await?);
+    }
+
+    Ok(repository.use_recovery_code(user.id, code).await?)
+}
+
+fn mfa_service(state: &AppState) -> Result<&MfaService, ApiError> {
+    state
+        .mfa
+        .as_deref()
+        .ok_or(ApiError::Disabled("two-factor authentication"))
+}
@@ -1,8 +1,29 @@
+pub mod articles;
 pub mod auth;
+pub mod comments;
 pub mod health;
+pub mod jwks;
+pub mod mfa;
+pub mod oauth;
+pub mod profiles;
+pub mod sessions;
+pub mod tags;
 
+pub use articles::{
+    create_article, delete_article, favorite_article, feed_articles, get_article, list_articles,
+    unfavorite_article, update_article,
+};
 pub use auth::{
     current_user, forgot_password, login, logout, refresh_token, register, reset_password,
-    verify_email,
+    update_user, verify_email,
 };
+pub use comments::{add_comment, delete_comment, list_comments};
 pub use health::health_check;
+pub use jwks::jwks;
+pub use mfa::{
+    confirm_mfa, disable_mfa, enroll_mfa, mfa_status, regenerate_recovery_codes, verify_mfa_login,
+};
+pub use oauth::{oauth_authorize, oauth_callback, oauth_exchange};
+pub use profiles::{follow_user, get_profile, unfollow_user};
+pub use sessions::{list_sessions, revoke_other_sessions, revoke_session};
+pub use tags::get_tags;
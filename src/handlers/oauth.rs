@@ -0,0 +1,268 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    response::Redirect,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use chrono::{Duration, Utc};
+use rand::{Rng, RngCore};
+use tracing::{info, instrument, warn};
+
+use super::auth::complete_login;
+use crate::{
+    auth::{
+        oauth::{ExternalIdentity, OAuthError},
+        password::hash_password,
+        tokens::{TokenKind, hash_token},
+    },
+    errors::{ApiError, Lang},
+    models::{ClientInfo, User},
+    repositories::UserRepositoryTrait,
+    schemas::{LoginResult, OAuthCallbackQuery, OAuthExchangeRequest},
+    state::AppState,
+};
+
+// How long the user has to sign in at the provider
+const STATE_TTL_MINUTES: i64 = 10;
+// How long the frontend has to exchange the code it was redirected with
+const LOGIN_CODE_TTL_SECS: i64 = 60;
+// Holds the digest of the state, binding the login to the browser that started it
+const STATE_COOKIE: &str = "oauth_state";
+const MIN_USERNAME_LEN: usize = 3;
+// Leaves room for the suffix that makes a taken username unique
+const MAX_USERNAME_BASE_LEN: usize = 40;
+
+// Starts the login, e.g. from a "Sign in with Google" link
+#[instrument(skip(state, jar))]
+pub async fn oauth_authorize(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Redirect), ApiError> {
+    let oauth_provider = state
+        .oauth
+        .provider(&provider)
+        .ok_or(ApiError::NotFound("provider"))?;
+
+    let request = oauth_provider.authorization_request().await?;
+    state
+        .oauth_repository
+        .create_state(
+            &provider,
+            &request.state,
+            &request.pkce_verifier,
+            request.nonce.as_deref(),
+            Utc::now() + Duration::minutes(STATE_TTL_MINUTES),
+        )
+        .await?;
+
+    let mut cookie = state_cookie(&state, &provider, hash_token(&request.state));
+    cookie.set_max_age(time::Duration::minutes(STATE_TTL_MINUTES));
+
+    Ok((jar.add(cookie), Redirect::to(&request.url)))
+}
+
+// The provider redirects the user here; sends them on to the frontend with a one-time code
+#[instrument(skip(state, jar, query))]
+pub async fn oauth_callback(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    jar: CookieJar,
+    Query(query): Query<OAuthCallbackQuery>,
+) -> Result<(CookieJar, Redirect), ApiError> {
+    let oauth_provider = state
+        .oauth
+        .provider(&provider)
+        .ok_or(ApiError::NotFound("provider"))?;
+
+    if let Some(error) = &query.error {
+        info!(error, "Identity provider did not authorize the login");
+        return Err(OAuthError::from_callback(error).into());
+    }
+    let (Some(code), Some(csrf_state)) = (&query.code, &query.state) else {
+        return Err(ApiError::BadRequest("missing code or state"));
+    };
+
+    // A state from another browser means someone tries to sign the user in to their account
+    if jar
+        .get(STATE_COOKIE)
+        .is_none_or(|cookie| cookie.value() != hash_token(csrf_state))
+    {
+        warn!("OAuth callback without the state cookie of its login");
+        return Err(ApiError::Unauthorized);
+    }
+
+    // An unknown state means the callback wasn't started here, or was already used
+    let login = state
+        .oauth_repository
+        .consume_state(&provider, csrf_state)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+
+    let identity = oauth_provider
+        .exchange_code(code, &login.pkce_verifier, login.nonce.as_deref())
+        .await
+        .inspect_err(|err| {
+            if err.is_rejection() {
+                warn!("Identity provider login rejected: {}", err);
+            }
+        })?;
+
+    let user = find_or_create_user(&state, &provider, identity).await?;
+
+    // The tokens must not end up in the browser's history or the server logs, so the frontend
+    // gets a short-lived code and fetches them with it
+    let login_code = TokenKind::OAuthLogin.generate();
+    state
+        .oauth_repository
+        .create_login_code(
+            user.id,
+            &login_code,
+            Utc::now() + Duration::seconds(LOGIN_CODE_TTL_SECS),
+        )
+        .await?;
+
+    Ok((
+        jar.remove(state_cookie(&state, &provider, "")),
+        Redirect::to(&oauth_provider.frontend_redirect(&login_code)),
+    ))
+}
+
+// Completes the login for the frontend; answers like `login`
+#[instrument(skip(state, client, payload))]
+pub async fn oauth_exchange(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(payload): Json<OAuthExchangeRequest>,
+) -> Result<Json<LoginResult>, ApiError> {
+    if !TokenKind::OAuthLogin.is_well_formed(&payload.code) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let user_id = state
+        .oauth_repository
+        .consume_login_code(&payload.code)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+    let user = state
+        .user_repository
+        .find_by_id(user_id)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+
+    Ok(Json(complete_login(&state, user, &client).await?))
+}
+
+// Only sent back to the provider's callback. Lax, as the callback is a top-level navigation
+// from the provider's site.
+fn state_cookie(state: &AppState, provider: &str, value: impl Into<String>) -> Cookie<'static> {
+    Cookie::build((STATE_COOKIE, value.into()))
+        .path(format!("/api/auth/oauth/{provider}/callback"))
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(state.config.server.base_url.starts_with("https://"))
+        .build()
+}
+
+// The user an identity is linked to. On its first login the identity is linked to the account
+// with the same email, or a new account is created for it.
+async fn find_or_create_user(
+    state: &AppState,
+    provider: &str,
+    identity: ExternalIdentity,
+) -> Result<User, ApiError> {
+    let uow = state.unit_of_work.begin().await?;
+
+    if let Some(linked) = uow
+        .oauth()
+        .find_identity(provider, &identity.subject)
+        .await?
+    {
+        let user = uow
+            .users()
+            .find_by_id(linked.user_id)
+            .await?
+            .ok_or(ApiError::Unauthorized)?;
+        uow.oauth().record_login(linked.id).await?;
+        uow.commit().await?;
+        return Ok(user);
+    }
+
+    // Only an address the provider has verified shows that the user owns it
+    let email = identity
+        .email
+        .as_deref()
+        .filter(|_| identity.email_verified)
+        .ok_or(ApiError::Unprocessable(
+            "the identity provider did not share a verified email address",
+        ))?
+        .to_lowercase();
+
+    // Providers don't necessarily report the address in the case it was registered with
+    let user = match uow.users().find_by_email_ignoring_case(&email).await? {
+        // Whoever registered an unverified account may not own the address; linking would let
+        // them keep access through its password once the owner signs in
+        Some(user) if !user.email_verified => return Err(ApiError::Conflict("email")),
+        Some(user) => {
+            info!(user_id = %user.id, "Linking identity to existing account");
+            user
+        }
+        None => {
+            let username =
+                available_username(uow.users(), identity.username.as_deref(), &email).await?;
+
+            // The account has no usable password until the user sets one with a password reset
+            let mut password = [0u8; 32];
+            rand::rng().fill_bytes(&mut password);
+            let password_hash = hash_password(&hex::encode(password))?;
+
+            let user = uow
+                .users()
+                .create(&username, &email, &password_hash, Lang::default())
+                .await?;
+            uow.email_verifications().verify_user_email(user.id).await?;
+            info!(user_id = %user.id, "Created account for identity");
+
+            User {
+                email_verified: true,
+                ..user
+            }
+        }
+    };
+
+    uow.oauth()
+        .create_identity(user.id, provider, &identity.subject, Some(&email))
+        .await?;
+    uow.commit().await?;
+
+    Ok(user)
+}
+
+// The provider's username or the email's local part, made unique with a numeric suffix if taken
+async fn available_username(
+    users: &dyn UserRepositoryTrait,
+    suggestion: Option<&str>,
+    email: &str,
+) -> Result<String, ApiError> {
+    let suggestion = suggestion.unwrap_or_else(|| email.split('@').next().unwrap_or_default());
+    let mut base: String = suggestion
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        .take(MAX_USERNAME_BASE_LEN)
+        .collect();
+    if base.len() < MIN_USERNAME_LEN {
+        base = "user".to_string();
+    }
+
+    if users.find_by_username(&base).await?.is_none() {
+        return Ok(base);
+    }
+    for _ in 0..5 {
+        let candidate = format!("{base}-{}", rand::rng().random_range(1000..10000));
+        if users.find_by_username(&candidate).await?.is_none() {
+            return Ok(candidate);
+        }
+    }
+
+    Err(ApiError::Conflict("username"))
+}
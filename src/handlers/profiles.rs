@@ -0,0 +1,88 @@
+use crate::{
+    auth::middleware::{OptionalAuth, RequireAuth},
+    errors::ApiError,
+    models::Profile,
+    schemas::{ProfileData, ProfileResponse},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use tracing::instrument;
+
+#[instrument(skip(state, viewer))]
+pub async fn get_profile(
+    State(state): State<AppState>,
+    OptionalAuth(viewer): OptionalAuth,
+    Path(username): Path<String>,
+) -> Result<Json<ProfileResponse>, ApiError> {
+    let profile = state
+        .profile_repository
+        .find_by_username(&username, viewer.map(|user| user.id))
+        .await?
+        .ok_or(ApiError::NotFound("profile"))?;
+
+    Ok(Json(ProfileResponse {
+        profile: ProfileData::from_profile(profile),
+    }))
+}
+
+#[instrument(skip(state, user))]
+pub async fn follow_user(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Path(username): Path<String>,
+) -> Result<Json<ProfileResponse>, ApiError> {
+    let profile = find_profile(&state, &username, user.id).await?;
+
+    // Users cannot follow themselves
+    if profile.user_id == user.id {
+        return Err(ApiError::Unprocessable("you cannot follow yourself"));
+    }
+
+    state
+        .profile_repository
+        .follow(user.id, profile.user_id)
+        .await?;
+
+    Ok(Json(ProfileResponse {
+        profile: ProfileData::from_profile(Profile {
+            following: true,
+            ..profile
+        }),
+    }))
+}
+
+#[instrument(skip(state, user))]
+pub async fn unfollow_user(
+    State(state): State<AppState>,
+    RequireAuth(user): RequireAuth,
+    Path(username): Path<String>,
+) -> Result<Json<ProfileResponse>, ApiError> {
+    let profile = find_profile(&state, &username, user.id).await?;
+
+    state
+        .profile_repository
+        .unfollow(user.id, profile.user_id)
+        .await?;
+
+    Ok(Json(ProfileResponse {
+        profile: ProfileData::from_profile(Profile {
+            following: false,
+            ..profile
+        }),
+    }))
+}
+
+async fn find_profile(
+    state: &AppState,
+    username: &str,
+    viewer_id: uuid::Uuid,
+) -> Result<Profile, ApiError> {
+    state
+        .profile_repository
+        .find_by_username(username, Some(viewer_id))
+        .await?
+        .ok_or(ApiError::NotFound("profile"))
+}
@@ -0,0 +1,83 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use crate::{
+    auth::middleware::CurrentSession,
+    errors::ApiError,
+    schemas::{LogoutResponse, SessionData, SessionsResponse},
+    state::AppState,
+};
+
+#[instrument(skip(state, session), fields(user_id = %session.user.id))]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    session: CurrentSession,
+) -> Result<Json<SessionsResponse>, ApiError> {
+    let sessions = state
+        .refresh_token_repository
+        .list_sessions(session.user.id)
+        .await?
+        .into_iter()
+        .map(|s| SessionData::from_session(s, session.session_id))
+        .collect();
+
+    Ok(Json(SessionsResponse { sessions }))
+}
+
+#[instrument(skip(state, session), fields(user_id = %session.user.id))]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    session: CurrentSession,
+    Path(id): Path<Uuid>,
+) -> Result<Json<LogoutResponse>, ApiError> {
+    // Another user's session looks the same as one that doesn't exist
+    let revoked = state
+        .refresh_token_repository
+        .revoke_session(session.user.id, id)
+        .await?;
+    if revoked.is_empty() {
+        return Err(ApiError::NotFound("session"));
+    }
+
+    state
+        .access_token_denylist
+        .revoke_issued_with(&revoked)
+        .await?;
+
+    Ok(Json(LogoutResponse {
+        message: "Session revoked successfully".to_string(),
+    }))
+}
+
+// "Log out everywhere else". If the current session isn't known, e.g. the access token predates
+// session tracking, every session is revoked, including the caller's own.
+#[instrument(skip(state, session), fields(user_id = %session.user.id))]
+pub async fn revoke_other_sessions(
+    State(state): State<AppState>,
+    session: CurrentSession,
+) -> Result<Json<LogoutResponse>, ApiError> {
+    let revoked = state
+        .refresh_token_repository
+        .revoke_other_sessions(session.user.id, session.session_id)
+        .await?;
+
+    state
+        .access_token_denylist
+        .revoke_issued_with(&revoked)
+        .await?;
+
+    let sessions = revoked
+        .iter()
+        .map(|refresh_token| refresh_token.family_id)
+        .collect::<std::collections::HashSet<_>>()
+        .len();
+    info!(sessions, "Revoked other sessions");
+
+    Ok(Json(LogoutResponse {
+        message: format!("Revoked {} other session(s)", sessions),
+    }))
+}
@@ -0,0 +1,13 @@
+use crate::{errors::ApiError, schemas::TagsResponse, state::AppState};
+use axum::{Json, extract::State};
+use tracing::instrument;
+
+// Number of tags shown in the sidebar
+const POPULAR_TAGS_LIMIT: i64 = 20;
+
+#[instrument(skip(state))]
+pub async fn get_tags(State(state): State<AppState>) -> Result<Json<TagsResponse>, ApiError> {
+    let tags = state.tag_repository.popular(POPULAR_TAGS_LIMIT).await?;
+
+    Ok(Json(TagsResponse { tags }))
+}
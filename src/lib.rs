@@ -1,4 +1,5 @@
 pub mod auth;
+pub mod config;
 pub mod errors;
 pub mod handlers;
 pub mod metrics;
@@ -1,14 +1,15 @@
+use std::net::SocketAddr;
+
 use axum::{
     BoxError, Router,
     error_handling::HandleErrorLayer,
     http::{
-        Method, Request,
+        HeaderValue, Method,
         header::{AUTHORIZATION, CONTENT_TYPE},
     },
-    routing::{get, post},
+    routing::{delete, get, post},
 };
 use opentelemetry::global;
-use std::{env, time::Duration};
 use tower::{ServiceBuilder, timeout::TimeoutLayer};
 use tower_http::{
     compression::CompressionLayer, cors::CorsLayer,
@@ -17,15 +18,22 @@ use tower_http::{
 use tracing::info;
 
 use realworld_axum_api::{
-    auth::middleware::track_metrics,
+    auth::middleware::{request_span, track_metrics},
+    config::{Config, ConfigError},
     errors::AppError,
     handlers::{
-        current_user, forgot_password, health_check, login, logout, refresh_token, register,
-        reset_password, verify_email,
+        add_comment, confirm_mfa, create_article, current_user, delete_article, delete_comment,
+        disable_mfa, enroll_mfa, favorite_article, feed_articles, follow_user, forgot_password,
+        get_article, get_profile, get_tags, health_check, jwks, list_articles, list_comments,
+        list_sessions, login, logout, mfa_status, oauth_authorize, oauth_callback, oauth_exchange,
+        refresh_token, regenerate_recovery_codes, register, reset_password, revoke_other_sessions,
+        revoke_session, unfavorite_article, unfollow_user, update_article, update_user,
+        verify_email, verify_mfa_login,
     },
     metrics::Metrics,
     otlp,
-    state::AppState,
+    services::EmailOutboxWorker,
+    state::{AppState, StateError},
     views::{greeting_handler, index_handler, start_handler},
 };
 
@@ -46,32 +54,50 @@ async fn handle_timeout_error(err: BoxError) -> (axum::http::StatusCode, String)
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     dotenvy::dotenv().ok();
-    let endpoint = env::var("OLTP_ENDPOINT").ok();
-    let token = env::var("OLTP_TOKEN").ok();
+    let config = Config::load().map_err(Error::Config)?;
 
     let logger_level = Some("info".to_owned());
-    let meter_provider = otlp::init_tracing(logger_level, endpoint, token).unwrap();
+    let meter_provider = otlp::init_tracing(
+        logger_level,
+        config.otlp.endpoint.clone(),
+        config.otlp.token.clone(),
+    )
+    .unwrap();
     let meter = meter_provider.as_ref().map(|_| global::meter("my_meter"));
     let metrics = meter.map(Metrics::new);
 
-    let database_url =
-        env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env file or environment");
+    let bind_addr = config.server.bind_addr;
+    let request_timeout = config.server.request_timeout;
+    // Origins were validated when the config was loaded
+    let cors_origins: Vec<HeaderValue> = config
+        .server
+        .cors_origins
+        .iter()
+        .filter_map(|origin| origin.parse().ok())
+        .collect();
 
-    let app_state = AppState::new(&database_url, metrics)
-        .await
-        .expect("Failed to connect to database");
+    let app_state = AppState::new(config, metrics).await.map_err(Error::State)?;
 
     info!("Connected to database successfully!");
 
+    EmailOutboxWorker::new(
+        app_state.email_outbox_repository.clone(),
+        app_state.email_service.clone(),
+        app_state.config.email.outbox.clone(),
+        app_state.metrics.clone(),
+    )
+    .spawn();
+
+    app_state
+        .access_token_denylist
+        .clone()
+        .spawn_sync(app_state.config.jwt.revocation_sync_interval);
+
     // 跨域
     let cors = CorsLayer::new()
-        .allow_origin(
-            "https://example.com"
-                .parse::<axum::http::HeaderValue>()
-                .unwrap(),
-        )
-        .allow_methods(vec![Method::GET, Method::POST])
-        .allow_headers(vec![CONTENT_TYPE])
+        .allow_origin(cors_origins)
+        .allow_methods(vec![Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers(vec![CONTENT_TYPE, AUTHORIZATION])
         .expose_headers(vec![CONTENT_TYPE]);
     // 压缩头部
     // let predicate = DefaultPredicate::new()
@@ -82,25 +108,60 @@ async fn main() -> Result<(), Error> {
 
     // 屏蔽日志中 Token 敏感信息， 需要sensitive-headers支持
     let sensitive = SetSensitiveRequestHeadersLayer::new(vec![AUTHORIZATION]);
-    let trace = TraceLayer::new_for_http().make_span_with(
-        |request: &Request<_>| {
-            tracing::info_span!("http_req", method = %request.method(), uri = %request.uri())
-        },
-    );
-    let timeout = TimeoutLayer::new(Duration::from_secs(30));
+    let trace = TraceLayer::new_for_http().make_span_with(request_span);
+    let timeout = TimeoutLayer::new(request_timeout);
     let app = Router::new()
         .route("/", get(start_handler))
         .route("/{lang}/index.html", get(index_handler))
         .route("/{lang}/greet-me.html", get(greeting_handler))
         .route("/health", get(health_check))
+        .route("/.well-known/jwks.json", get(jwks))
         .route("/api/users", post(register))
         .route("/api/users/login", post(login))
-        .route("/api/user", get(current_user))
+        .route("/api/users/login/mfa", post(verify_mfa_login))
+        .route("/api/user", get(current_user).put(update_user))
+        .route(
+            "/api/user/sessions",
+            get(list_sessions).delete(revoke_other_sessions),
+        )
+        .route("/api/user/sessions/{id}", delete(revoke_session))
+        .route("/api/user/mfa", get(mfa_status))
+        .route("/api/user/mfa/enroll", post(enroll_mfa))
+        .route("/api/user/mfa/confirm", post(confirm_mfa))
+        .route("/api/user/mfa/disable", post(disable_mfa))
+        .route(
+            "/api/user/mfa/recovery-codes",
+            post(regenerate_recovery_codes),
+        )
         .route("/api/auth/verify-email", get(verify_email))
         .route("/api/auth/forgot-password", post(forgot_password))
         .route("/api/auth/reset-password", post(reset_password))
         .route("/api/auth/refresh", post(refresh_token))
         .route("/api/auth/logout", post(logout))
+        .route("/api/auth/oauth/{provider}/authorize", get(oauth_authorize))
+        .route("/api/auth/oauth/{provider}/callback", get(oauth_callback))
+        .route("/api/auth/oauth/exchange", post(oauth_exchange))
+        .route("/api/articles", get(list_articles).post(create_article))
+        .route("/api/articles/feed", get(feed_articles))
+        .route(
+            "/api/articles/{slug}",
+            get(get_article).put(update_article).delete(delete_article),
+        )
+        .route(
+            "/api/articles/{slug}/favorite",
+            post(favorite_article).delete(unfavorite_article),
+        )
+        .route(
+            "/api/articles/{slug}/comments",
+            get(list_comments).post(add_comment),
+        )
+        .route("/api/articles/{slug}/comments/{id}", delete(delete_comment))
+        .route("/api/profiles/{username}", get(get_profile))
+        .route("/api/tags", get(get_tags))
+        .route(
+            "/api/profiles/{username}/follow",
+            post(follow_user).delete(unfollow_user),
+        )
         .fallback(|| async { AppError::NotFound })
         .layer(axum::middleware::from_fn_with_state(
             app_state.clone(),
@@ -117,14 +178,19 @@ async fn main() -> Result<(), Error> {
         )
         .layer(trace);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
+    let listener = tokio::net::TcpListener::bind(bind_addr)
         .await
         .map_err(Error::Bind)?;
     if let Ok(addr) = listener.local_addr() {
         info!("Server running on http://{addr}/");
     }
 
-    axum::serve(listener, app).await.map_err(Error::Run)?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .map_err(Error::Run)?;
     if let Some(meter_provider) = meter_provider {
         meter_provider.shutdown().map_err(Error::OTel)?;
     }
@@ -133,6 +199,10 @@ async fn main() -> Result<(), Error> {
 
 #[derive(displaydoc::Display, pretty_error_debug::Debug, thiserror::Error)]
 enum Error {
+    /// invalid configuration
+    Config(#[source] ConfigError),
+    /// could not initialize application state
+    State(#[source] StateError),
     /// could not bind socket
     Bind(#[source] std::io::Error),
     /// could not run server
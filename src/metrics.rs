@@ -1,11 +1,15 @@
 use opentelemetry::{
     KeyValue,
-    metrics::{Counter, Meter},
+    metrics::{Counter, Gauge, Meter},
 };
 
 #[derive(Clone)]
 pub struct Metrics {
     pub http_requests_total: Counter<u64>,
+    pub emails_sent_total: Counter<u64>,
+    pub email_delivery_failures_total: Counter<u64>,
+    pub emails_dead_lettered_total: Counter<u64>,
+    pub email_outbox_pending: Gauge<u64>,
 }
 
 impl Metrics {
@@ -14,9 +18,29 @@ impl Metrics {
             .u64_counter("http_requests_total")
             .with_description("Total number of HTTP requests")
             .build();
+        let emails_sent_total = meter
+            .u64_counter("emails_sent_total")
+            .with_description("Total number of emails delivered from the outbox")
+            .build();
+        let email_delivery_failures_total = meter
+            .u64_counter("email_delivery_failures_total")
+            .with_description("Total number of failed email delivery attempts")
+            .build();
+        let emails_dead_lettered_total = meter
+            .u64_counter("emails_dead_lettered_total")
+            .with_description("Total number of emails given up on after too many attempts")
+            .build();
+        let email_outbox_pending = meter
+            .u64_gauge("email_outbox_pending")
+            .with_description("Number of emails waiting in the outbox")
+            .build();
 
         Self {
             http_requests_total,
+            emails_sent_total,
+            email_delivery_failures_total,
+            emails_dead_lettered_total,
+            email_outbox_pending,
         }
     }
 
@@ -27,4 +51,23 @@ impl Metrics {
         ];
         self.http_requests_total.add(1, &attributes);
     }
+
+    pub fn record_email_sent(&self, kind: &'static str) {
+        self.emails_sent_total
+            .add(1, &[KeyValue::new("kind", kind)]);
+    }
+
+    pub fn record_email_delivery_failure(&self, kind: &'static str) {
+        self.email_delivery_failures_total
+            .add(1, &[KeyValue::new("kind", kind)]);
+    }
+
+    pub fn record_email_dead_lettered(&self, kind: &'static str) {
+        self.emails_dead_lettered_total
+            .add(1, &[KeyValue::new("kind", kind)]);
+    }
+
+    pub fn record_email_outbox_pending(&self, pending: u64) {
+        self.email_outbox_pending.record(pending, &[]);
+    }
 }
@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Article {
+    pub id: Uuid,
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub author_id: Uuid,
+    pub favorites_count: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Article {
+    pub fn is_authored_by(&self, user_id: Uuid) -> bool {
+        self.author_id == user_id
+    }
+}
+
+// Article joined with its author's public profile, as returned by listing queries
+#[derive(Debug, Clone, FromRow)]
+pub struct ArticleView {
+    #[sqlx(flatten)]
+    pub article: Article,
+    pub author_username: String,
+    pub author_bio: Option<String>,
+    pub author_image: Option<String>,
+    pub following: bool,
+    pub favorited: bool,
+    pub tag_list: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ArticleFilter {
+    pub tag: Option<String>,
+    pub author: Option<String>,
+    pub favorited: Option<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Comment {
+    pub id: Uuid,
+    pub body: String,
+    pub article_id: Uuid,
+    pub author_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Comment {
+    pub fn is_authored_by(&self, user_id: Uuid) -> bool {
+        self.author_id == user_id
+    }
+}
+
+// Comment joined with its author's public profile
+#[derive(Debug, Clone, FromRow)]
+pub struct CommentView {
+    #[sqlx(flatten)]
+    pub comment: Comment,
+    pub author_username: String,
+    pub author_bio: Option<String>,
+    pub author_image: Option<String>,
+    pub following: bool,
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::Json};
+use uuid::Uuid;
+
+use crate::errors::Lang;
+
+// An email waiting in the outbox; the worker renders it right before delivery
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OutboxEmail {
+    Verification {
+        username: String,
+        token: OutboxToken,
+        #[serde(default)]
+        lang: Lang,
+    },
+    PasswordReset {
+        username: String,
+        token: OutboxToken,
+        #[serde(default)]
+        lang: Lang,
+    },
+    SecurityAlert {
+        username: String,
+        #[serde(default)]
+        lang: Lang,
+        // When the login that started the revoked session happened
+        #[serde(default)]
+        session_started_at: Option<DateTime<Utc>>,
+        // Device and address the revoked session was last used from
+        #[serde(default)]
+        user_agent: Option<String>,
+        #[serde(default)]
+        ip_address: Option<String>,
+    },
+}
+
+impl OutboxEmail {
+    // Used as a metrics label and in logs, never includes the token
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OutboxEmail::Verification { .. } => "verification",
+            OutboxEmail::PasswordReset { .. } => "password_reset",
+            OutboxEmail::SecurityAlert { .. } => "security_alert",
+        }
+    }
+}
+
+/// The token of a verification or reset email, encrypted with the outbox key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OutboxToken {
+    // Base64 of the nonce followed by the ciphertext, see `OutboxCipher`
+    Sealed { sealed: String },
+    // Queued before tokens were encrypted
+    Plain(String),
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct OutboxMessage {
+    pub id: Uuid,
+    pub recipient: String,
+    pub email: Json<OutboxEmail>,
+    // Includes the attempt currently in progress
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// A user's TOTP authenticator, pending until `enabled_at` is set
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserTotp {
+    pub user_id: Uuid,
+    pub secret_encrypted: Vec<u8>,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserTotp {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_at.is_some()
+    }
+}
+
+// A login waiting for its second factor
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MfaChallenge {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub failed_attempts: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MfaChallenge {
+    pub fn is_expired(&self) -> bool {
+        Utc::now() > self.expires_at
+    }
+}
@@ -1,9 +1,25 @@
+pub mod article;
+pub mod comment;
+pub mod email_outbox;
 pub mod email_verification_token;
+pub mod mfa;
 pub mod password_reset_token;
+pub mod profile;
 pub mod refresh_token;
+pub mod revoked_access_token;
+pub mod session;
 pub mod user;
+pub mod user_identity;
 
+pub use article::{Article, ArticleFilter, ArticleView};
+pub use comment::{Comment, CommentView};
+pub use email_outbox::{OutboxEmail, OutboxMessage, OutboxToken};
 pub use email_verification_token::EmailVerificationToken;
+pub use mfa::{MfaChallenge, UserTotp};
 pub use password_reset_token::PasswordResetToken;
+pub use profile::Profile;
 pub use refresh_token::RefreshToken;
+pub use revoked_access_token::RevokedAccessToken;
+pub use session::{ClientInfo, Session};
 pub use user::User;
+pub use user_identity::{OAuthState, UserIdentity};
@@ -7,7 +7,7 @@ use uuid::Uuid;
 pub struct PasswordResetToken {
     pub id: Uuid,
     pub user_id: Uuid,
-    pub token: String,
+    pub token_hash: String,
     pub expires_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
 }
@@ -0,0 +1,25 @@
+use uuid::Uuid;
+
+use super::User;
+
+// A user's public profile as seen by another (possibly anonymous) user
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub user_id: Uuid,
+    pub username: String,
+    pub bio: Option<String>,
+    pub image: Option<String>,
+    pub following: bool,
+}
+
+impl Profile {
+    pub fn from_user(user: User, following: bool) -> Self {
+        Self {
+            user_id: user.id,
+            username: user.username,
+            bio: user.bio,
+            image: user.image,
+            following,
+        }
+    }
+}
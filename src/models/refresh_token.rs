@@ -7,12 +7,22 @@ use uuid::Uuid;
 pub struct RefreshToken {
     pub id: Uuid,
     pub user_id: Uuid,
-    pub token: String,
+    pub token_hash: String,
     pub expires_at: DateTime<Utc>,
     pub is_used: bool,
     pub used_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub last_used_at: DateTime<Utc>,
+    // Shared by all tokens rotated from the same login
+    pub family_id: Uuid,
+    // The token this one was rotated from; `None` for the first token of a family
+    pub parent_id: Option<Uuid>,
+    // The access token issued together with this token; `None` for tokens issued before
+    // access tokens could be revoked
+    pub access_token_jti: Option<Uuid>,
+    pub access_token_expires_at: Option<DateTime<Utc>>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
 }
 
 impl RefreshToken {
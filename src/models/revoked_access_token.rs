@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RevokedAccessToken {
+    pub jti: Uuid,
+    pub user_id: Uuid,
+    // The entry can be pruned once the token would have expired anyway
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: DateTime<Utc>,
+}
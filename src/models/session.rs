@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// A login session, i.e. a refresh token rotation family, as shown to its user
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Session {
+    // The family id
+    pub id: Uuid,
+    // Client of the most recent login or refresh
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+// The client a refresh token is issued to
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::errors::Lang;
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: Uuid,
@@ -12,6 +14,8 @@ pub struct User {
     pub bio: Option<String>,
     pub image: Option<String>,
     pub email_verified: bool,
+    // Language of the emails we send to this user
+    pub lang: Lang,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -0,0 +1,367 @@
+use async_trait::async_trait;
+use sqlx::{FromRow, PgPool};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::db::{Db, SharedTransaction};
+use super::traits::ArticleRepositoryTrait;
+use crate::models::{Article, ArticleFilter, ArticleView};
+
+// Columns of `ArticleView`; `$1` is always the viewing user (NULL for anonymous requests)
+const ARTICLE_VIEW_COLUMNS: &str = r#"
+    a.id, a.slug, a.title, a.description, a.body, a.author_id, a.favorites_count,
+    a.created_at, a.updated_at,
+    u.username AS author_username,
+    u.bio AS author_bio,
+    u.image AS author_image,
+    EXISTS (
+        SELECT 1 FROM follows fv
+        WHERE fv.follower_id = $1 AND fv.followee_id = a.author_id
+    ) AS following,
+    EXISTS (
+        SELECT 1 FROM favorites fav
+        WHERE fav.user_id = $1 AND fav.article_id = a.id
+    ) AS favorited,
+    ARRAY(
+        SELECT t.name FROM article_tags atg
+        JOIN tags t ON t.id = atg.tag_id
+        WHERE atg.article_id = a.id
+        ORDER BY t.name
+    ) AS tag_list
+"#;
+
+// Conditions of `list`; `$2` is the author, `$3` the user who favorited and `$4` the tag
+const ARTICLE_FILTER: &str = r#"
+    ($2::VARCHAR IS NULL OR u.username = $2)
+    AND ($3::VARCHAR IS NULL OR EXISTS (
+        SELECT 1 FROM favorites ff
+        JOIN users fu ON fu.id = ff.user_id
+        WHERE ff.article_id = a.id AND fu.username = $3
+    ))
+    AND ($4::VARCHAR IS NULL OR EXISTS (
+        SELECT 1 FROM article_tags ft
+        JOIN tags tt ON tt.id = ft.tag_id
+        WHERE ft.article_id = a.id AND tt.name = LOWER($4)
+    ))
+"#;
+
+#[derive(FromRow)]
+struct ArticleListRow {
+    #[sqlx(flatten)]
+    view: ArticleView,
+    total_count: i64,
+}
+
+fn into_page(rows: Vec<ArticleListRow>) -> (Vec<ArticleView>, i64) {
+    let total = rows.first().map(|row| row.total_count).unwrap_or(0);
+    let articles = rows.into_iter().map(|row| row.view).collect();
+    (articles, total)
+}
+
+#[derive(Clone)]
+pub struct ArticleRepository {
+    db: Db,
+}
+
+impl ArticleRepository {
+    pub fn new(db: PgPool) -> Self {
+        Self { db: Db::Pool(db) }
+    }
+
+    pub(super) fn in_transaction(tx: SharedTransaction) -> Self {
+        Self {
+            db: Db::Transaction(tx),
+        }
+    }
+}
+
+#[async_trait]
+impl ArticleRepositoryTrait for ArticleRepository {
+    #[instrument(skip(self, description, body))]
+    async fn create(
+        &self,
+        author_id: Uuid,
+        slug: &str,
+        title: &str,
+        description: &str,
+        body: &str,
+    ) -> Result<Option<Article>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let article = sqlx::query_as::<_, Article>(
+            r#"
+            INSERT INTO articles (author_id, slug, title, description, body)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (slug) DO NOTHING
+            RETURNING id, slug, title, description, body, author_id, favorites_count,
+                      created_at, updated_at
+            "#,
+        )
+        .bind(author_id)
+        .bind(slug)
+        .bind(title)
+        .bind(description)
+        .bind(body)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(article)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_slug(&self, slug: &str) -> Result<Option<Article>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let article = sqlx::query_as::<_, Article>(
+            r#"
+            SELECT id, slug, title, description, body, author_id, favorites_count,
+                   created_at, updated_at
+            FROM articles
+            WHERE slug = $1
+            "#,
+        )
+        .bind(slug)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(article)
+    }
+
+    #[instrument(skip(self, description, body))]
+    async fn update(
+        &self,
+        id: Uuid,
+        slug: Option<&str>,
+        title: Option<&str>,
+        description: Option<&str>,
+        body: Option<&str>,
+    ) -> Result<Option<Article>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let article = sqlx::query_as::<_, Article>(
+            r#"
+            UPDATE articles
+            SET slug = COALESCE($2, slug),
+                title = COALESCE($3, title),
+                description = COALESCE($4, description),
+                body = COALESCE($5, body)
+            WHERE id = $1
+            RETURNING id, slug, title, description, body, author_id, favorites_count,
+                      created_at, updated_at
+            "#,
+        )
+        .bind(id)
+        .bind(slug)
+        .bind(title)
+        .bind(description)
+        .bind(body)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(article)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM articles
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn find_view_by_slug(
+        &self,
+        slug: &str,
+        viewer_id: Option<Uuid>,
+    ) -> Result<Option<ArticleView>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let sql = format!(
+            r#"
+            SELECT {ARTICLE_VIEW_COLUMNS}
+            FROM articles a
+            JOIN users u ON u.id = a.author_id
+            WHERE a.slug = $2
+            "#
+        );
+
+        let article = sqlx::query_as::<_, ArticleView>(&sql)
+            .bind(viewer_id)
+            .bind(slug)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        Ok(article)
+    }
+
+    #[instrument(skip(self))]
+    async fn list(
+        &self,
+        filter: &ArticleFilter,
+        viewer_id: Option<Uuid>,
+    ) -> Result<(Vec<ArticleView>, i64), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        // COUNT(*) OVER () gives the total number of matches before LIMIT/OFFSET,
+        // so the page and the count usually come back in a single round trip
+        let sql = format!(
+            r#"
+            SELECT {ARTICLE_VIEW_COLUMNS},
+                   COUNT(*) OVER () AS total_count
+            FROM articles a
+            JOIN users u ON u.id = a.author_id
+            WHERE {ARTICLE_FILTER}
+            ORDER BY a.created_at DESC
+            LIMIT $5 OFFSET $6
+            "#
+        );
+
+        let rows = sqlx::query_as::<_, ArticleListRow>(&sql)
+            .bind(viewer_id)
+            .bind(filter.author.as_deref())
+            .bind(filter.favorited.as_deref())
+            .bind(filter.tag.as_deref())
+            .bind(filter.limit)
+            .bind(filter.offset)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        // A page past the end has no row to carry the count
+        if rows.is_empty() && filter.offset > 0 {
+            let sql = format!(
+                r#"
+                SELECT COUNT(*)
+                FROM articles a
+                JOIN users u ON u.id = a.author_id
+                WHERE {ARTICLE_FILTER}
+                "#
+            );
+
+            // `$1` is unused here, it is bound so the filter's placeholders line up
+            let total = sqlx::query_scalar::<_, i64>(&sql)
+                .bind(viewer_id)
+                .bind(filter.author.as_deref())
+                .bind(filter.favorited.as_deref())
+                .bind(filter.tag.as_deref())
+                .fetch_one(&mut *conn)
+                .await?;
+
+            return Ok((Vec::new(), total));
+        }
+
+        Ok(into_page(rows))
+    }
+
+    #[instrument(skip(self))]
+    async fn feed(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<ArticleView>, i64), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        // Driven by the follows primary key (follower_id, followee_id) and
+        // idx_articles_author_id, so it never scans articles from unfollowed authors
+        let sql = format!(
+            r#"
+            SELECT {ARTICLE_VIEW_COLUMNS},
+                   COUNT(*) OVER () AS total_count
+            FROM follows f
+            JOIN articles a ON a.author_id = f.followee_id
+            JOIN users u ON u.id = a.author_id
+            WHERE f.follower_id = $1
+            ORDER BY a.created_at DESC
+            LIMIT $2 OFFSET $3
+            "#
+        );
+
+        let rows = sqlx::query_as::<_, ArticleListRow>(&sql)
+            .bind(user_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        // A page past the end has no row to carry the count
+        if rows.is_empty() && offset > 0 {
+            let total = sqlx::query_scalar::<_, i64>(
+                r#"
+                SELECT COUNT(*)
+                FROM follows f
+                JOIN articles a ON a.author_id = f.followee_id
+                WHERE f.follower_id = $1
+                "#,
+            )
+            .bind(user_id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+            return Ok((Vec::new(), total));
+        }
+
+        Ok(into_page(rows))
+    }
+
+    #[instrument(skip(self))]
+    async fn favorite(&self, article_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        // The count only moves when the favorites row was actually inserted, and the
+        // increment is applied under the article's row lock, so concurrent requests
+        // can neither double count nor lose updates
+        sqlx::query(
+            r#"
+            WITH inserted AS (
+                INSERT INTO favorites (user_id, article_id)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING
+                RETURNING article_id
+            )
+            UPDATE articles
+            SET favorites_count = favorites_count + 1
+            WHERE id IN (SELECT article_id FROM inserted)
+            "#,
+        )
+        .bind(user_id)
+        .bind(article_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn unfavorite(&self, article_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        sqlx::query(
+            r#"
+            WITH deleted AS (
+                DELETE FROM favorites
+                WHERE user_id = $1 AND article_id = $2
+                RETURNING article_id
+            )
+            UPDATE articles
+            SET favorites_count = favorites_count - 1
+            WHERE id IN (SELECT article_id FROM deleted)
+            "#,
+        )
+        .bind(user_id)
+        .bind(article_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
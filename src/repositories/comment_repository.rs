@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::traits::CommentRepositoryTrait;
+use crate::models::{Comment, CommentView};
+
+#[derive(Clone)]
+pub struct CommentRepository {
+    db: PgPool,
+}
+
+impl CommentRepository {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl CommentRepositoryTrait for CommentRepository {
+    #[instrument(skip(self, body))]
+    async fn create(
+        &self,
+        article_id: Uuid,
+        author_id: Uuid,
+        body: &str,
+    ) -> Result<Comment, sqlx::Error> {
+        let comment = sqlx::query_as::<_, Comment>(
+            r#"
+            INSERT INTO comments (article_id, author_id, body)
+            VALUES ($1, $2, $3)
+            RETURNING id, body, article_id, author_id, created_at, updated_at
+            "#,
+        )
+        .bind(article_id)
+        .bind(author_id)
+        .bind(body)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(comment)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Comment>, sqlx::Error> {
+        let comment = sqlx::query_as::<_, Comment>(
+            r#"
+            SELECT id, body, article_id, author_id, created_at, updated_at
+            FROM comments
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(comment)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_for_article(
+        &self,
+        article_id: Uuid,
+        viewer_id: Option<Uuid>,
+    ) -> Result<Vec<CommentView>, sqlx::Error> {
+        let comments = sqlx::query_as::<_, CommentView>(
+            r#"
+            SELECT c.id, c.body, c.article_id, c.author_id, c.created_at, c.updated_at,
+                   u.username AS author_username,
+                   u.bio AS author_bio,
+                   u.image AS author_image,
+                   EXISTS (
+                       SELECT 1 FROM follows f
+                       WHERE f.follower_id = $2 AND f.followee_id = c.author_id
+                   ) AS following
+            FROM comments c
+            JOIN users u ON u.id = c.author_id
+            WHERE c.article_id = $1
+            ORDER BY c.created_at ASC
+            "#,
+        )
+        .bind(article_id)
+        .bind(viewer_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(comments)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM comments
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use sqlx::{PgConnection, PgPool, Postgres, Transaction, pool::PoolConnection};
+use tokio::sync::{Mutex, MutexGuard};
+
+// A transaction shared by all repositories of one unit of work; `None` once it has finished
+pub(super) type SharedTransaction = Arc<Mutex<Option<Transaction<'static, Postgres>>>>;
+
+/// Where a repository runs its queries: straight on the pool, or inside a unit of work's
+/// transaction.
+#[derive(Clone)]
+pub(super) enum Db {
+    Pool(PgPool),
+    Transaction(SharedTransaction),
+}
+
+impl Db {
+    pub(super) async fn conn(&self) -> Result<DbConn<'_>, sqlx::Error> {
+        match self {
+            Db::Pool(pool) => Ok(DbConn::Pool(pool.acquire().await?)),
+            Db::Transaction(tx) => {
+                let guard = tx.lock().await;
+                if guard.is_none() {
+                    return Err(sqlx::Error::Protocol(
+                        "unit of work has already been committed".to_string(),
+                    ));
+                }
+                Ok(DbConn::Transaction(guard))
+            }
+        }
+    }
+}
+
+// Dereferences to the connection to run queries on, e.g. `.fetch_one(&mut *conn)`
+pub(super) enum DbConn<'a> {
+    Pool(PoolConnection<Postgres>),
+    Transaction(MutexGuard<'a, Option<Transaction<'static, Postgres>>>),
+}
+
+impl Deref for DbConn<'_> {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            DbConn::Pool(conn) => conn,
+            // `Db::conn` checked that the transaction is still open
+            DbConn::Transaction(tx) => tx.as_ref().expect("transaction is open"),
+        }
+    }
+}
+
+impl DerefMut for DbConn<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            DbConn::Pool(conn) => conn,
+            DbConn::Transaction(tx) => tx.as_mut().expect("transaction is open"),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, types::Json};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::db::{Db, SharedTransaction};
+use super::traits::EmailOutboxRepositoryTrait;
+use crate::models::{OutboxEmail, OutboxMessage};
+
+#[derive(Clone)]
+pub struct EmailOutboxRepository {
+    db: Db,
+}
+
+impl EmailOutboxRepository {
+    pub fn new(db: PgPool) -> Self {
+        Self { db: Db::Pool(db) }
+    }
+
+    pub(super) fn in_transaction(tx: SharedTransaction) -> Self {
+        Self {
+            db: Db::Transaction(tx),
+        }
+    }
+}
+
+#[async_trait]
+impl EmailOutboxRepositoryTrait for EmailOutboxRepository {
+    #[instrument(skip(self, email), fields(kind = email.kind()))]
+    async fn enqueue(&self, recipient: &str, email: &OutboxEmail) -> Result<Uuid, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO email_outbox (recipient, email)
+            VALUES ($1, $2)
+            RETURNING id
+            "#,
+        )
+        .bind(recipient)
+        .bind(Json(email))
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(id)
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_due(
+        &self,
+        limit: i64,
+        leased_until: DateTime<Utc>,
+    ) -> Result<Vec<OutboxMessage>, sqlx::Error> {
+        // SKIP LOCKED lets several workers poll the same table without double delivery
+        let mut conn = self.db.conn().await?;
+
+        let messages = sqlx::query_as::<_, OutboxMessage>(
+            r#"
+            UPDATE email_outbox
+            SET attempts = attempts + 1,
+                next_attempt_at = $2
+            WHERE id IN (
+                SELECT id
+                FROM email_outbox
+                WHERE status = 'pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, recipient, email, attempts, created_at
+            "#,
+        )
+        .bind(limit)
+        .bind(leased_until)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(messages)
+    }
+
+    #[instrument(skip(self))]
+    async fn mark_sent(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE email_outbox
+            SET status = 'sent',
+                sent_at = NOW(),
+                last_error = NULL,
+                email = email - 'token'
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn reschedule(
+        &self,
+        id: Uuid,
+        next_attempt_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE email_outbox
+            SET next_attempt_at = $2,
+                last_error = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(next_attempt_at)
+        .bind(error)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn mark_dead(&self, id: Uuid, error: &str) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE email_outbox
+            SET status = 'dead',
+                last_error = $2,
+                email = email - 'token'
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(error)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn count_pending(&self) -> Result<i64, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM email_outbox
+            WHERE status = 'pending'
+            "#,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(count)
+    }
+}
@@ -4,74 +4,88 @@ use sqlx::PgPool;
 use tracing::instrument;
 use uuid::Uuid;
 
+use super::db::{Db, SharedTransaction};
 use super::traits::EmailVerificationRepositoryTrait;
+use crate::auth::tokens::hash_token;
 use crate::models::EmailVerificationToken;
 
 #[derive(Clone)]
 pub struct EmailVerificationRepository {
-    db: PgPool,
+    db: Db,
 }
 
 impl EmailVerificationRepository {
     pub fn new(db: PgPool) -> Self {
-        Self { db }
+        Self { db: Db::Pool(db) }
+    }
+
+    pub(super) fn in_transaction(tx: SharedTransaction) -> Self {
+        Self {
+            db: Db::Transaction(tx),
+        }
     }
 }
 
 #[async_trait]
 impl EmailVerificationRepositoryTrait for EmailVerificationRepository {
-    #[instrument(skip(self))]
+    #[instrument(skip(self, token))]
     async fn create_token(
         &self,
         user_id: Uuid,
         token: &str,
         expires_at: DateTime<Utc>,
     ) -> Result<EmailVerificationToken, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let verification_token = sqlx::query_as::<_, EmailVerificationToken>(
             r#"
-            INSERT INTO email_verification_tokens (user_id, token, expires_at)
+            INSERT INTO email_verification_tokens (user_id, token_hash, expires_at)
             VALUES ($1, $2, $3)
-            RETURNING id, user_id, token, expires_at, created_at
+            RETURNING id, user_id, token_hash, expires_at, created_at
             "#,
         )
         .bind(user_id)
-        .bind(token)
+        .bind(hash_token(token))
         .bind(expires_at)
-        .fetch_one(&self.db)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(verification_token)
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self, token))]
     async fn find_by_token(
         &self,
         token: &str,
     ) -> Result<Option<EmailVerificationToken>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let verification_token = sqlx::query_as::<_, EmailVerificationToken>(
             r#"
-            SELECT id, user_id, token, expires_at, created_at
+            SELECT id, user_id, token_hash, expires_at, created_at
             FROM email_verification_tokens
-            WHERE token = $1
+            WHERE token_hash = $1
             "#,
         )
-        .bind(token)
-        .fetch_optional(&self.db)
+        .bind(hash_token(token))
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(verification_token)
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self, token))]
     async fn delete_token(&self, token: &str) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         sqlx::query(
             r#"
             DELETE FROM email_verification_tokens
-            WHERE token = $1
+            WHERE token_hash = $1
             "#,
         )
-        .bind(token)
-        .execute(&self.db)
+        .bind(hash_token(token))
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
@@ -79,6 +93,8 @@ impl EmailVerificationRepositoryTrait for EmailVerificationRepository {
 
     #[instrument(skip(self))]
     async fn verify_user_email(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         sqlx::query(
             r#"
             UPDATE users
@@ -87,7 +103,7 @@ impl EmailVerificationRepositoryTrait for EmailVerificationRepository {
             "#,
         )
         .bind(user_id)
-        .execute(&self.db)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
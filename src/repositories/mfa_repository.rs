@@ -0,0 +1,301 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::db::{Db, SharedTransaction};
+use super::traits::MfaRepositoryTrait;
+use crate::auth::{mfa::normalize_recovery_code, tokens::hash_token};
+use crate::models::{MfaChallenge, UserTotp};
+
+#[derive(Clone)]
+pub struct MfaRepository {
+    db: Db,
+}
+
+impl MfaRepository {
+    pub fn new(db: PgPool) -> Self {
+        Self { db: Db::Pool(db) }
+    }
+
+    pub(super) fn in_transaction(tx: SharedTransaction) -> Self {
+        Self {
+            db: Db::Transaction(tx),
+        }
+    }
+}
+
+#[async_trait]
+impl MfaRepositoryTrait for MfaRepository {
+    #[instrument(skip(self))]
+    async fn find_totp(&self, user_id: Uuid) -> Result<Option<UserTotp>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let totp = sqlx::query_as::<_, UserTotp>(
+            r#"
+            SELECT user_id, secret_encrypted, enabled_at, last_used_step, created_at
+            FROM user_totp
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(totp)
+    }
+
+    #[instrument(skip(self, secret_encrypted))]
+    async fn start_enrollment(
+        &self,
+        user_id: Uuid,
+        secret_encrypted: &[u8],
+    ) -> Result<Option<UserTotp>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        // A pending enrollment is replaced, an enabled authenticator is left alone
+        let totp = sqlx::query_as::<_, UserTotp>(
+            r#"
+            INSERT INTO user_totp (user_id, secret_encrypted)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET secret_encrypted = EXCLUDED.secret_encrypted,
+                last_used_step = NULL,
+                created_at = NOW()
+            WHERE user_totp.enabled_at IS NULL
+            RETURNING user_id, secret_encrypted, enabled_at, last_used_step, created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(secret_encrypted)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(totp)
+    }
+
+    #[instrument(skip(self))]
+    async fn enable_totp(&self, user_id: Uuid, step: i64) -> Result<bool, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE user_totp
+            SET enabled_at = NOW(), last_used_step = $2
+            WHERE user_id = $1 AND enabled_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(step)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE user_totp
+            SET last_used_step = $2
+            WHERE user_id = $1
+              AND enabled_at IS NOT NULL
+              AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+        )
+        .bind(user_id)
+        .bind(step)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_totp(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM mfa_recovery_codes
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM user_totp
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, codes))]
+    async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        codes: &[String],
+    ) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM mfa_recovery_codes
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+        let code_hashes: Vec<String> = codes
+            .iter()
+            .map(|code| hash_token(&normalize_recovery_code(code)))
+            .collect();
+        sqlx::query(
+            r#"
+            INSERT INTO mfa_recovery_codes (user_id, code_hash)
+            SELECT $1, code_hash FROM UNNEST($2::VARCHAR[]) AS code_hash
+            "#,
+        )
+        .bind(user_id)
+        .bind(&code_hashes)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, code))]
+    async fn use_recovery_code(&self, user_id: Uuid, code: &str) -> Result<bool, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE mfa_recovery_codes
+            SET used_at = NOW()
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(hash_token(&normalize_recovery_code(code)))
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn count_unused_recovery_codes(&self, user_id: Uuid) -> Result<i64, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM mfa_recovery_codes
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(count)
+    }
+
+    #[instrument(skip(self, token))]
+    async fn create_challenge(
+        &self,
+        user_id: Uuid,
+        token: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<MfaChallenge, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let challenge = sqlx::query_as::<_, MfaChallenge>(
+            r#"
+            INSERT INTO mfa_challenges (user_id, token_hash, expires_at)
+            VALUES ($1, $2, $3)
+            RETURNING id, user_id, token_hash, expires_at, failed_attempts, created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(hash_token(token))
+        .bind(expires_at)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(challenge)
+    }
+
+    #[instrument(skip(self, token))]
+    async fn consume_challenge(&self, token: &str) -> Result<Option<MfaChallenge>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let challenge = sqlx::query_as::<_, MfaChallenge>(
+            r#"
+            DELETE FROM mfa_challenges
+            WHERE token_hash = $1 AND expires_at > NOW()
+            RETURNING id, user_id, token_hash, expires_at, failed_attempts, created_at
+            "#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(challenge)
+    }
+
+    #[instrument(skip(self, token))]
+    async fn record_attempt(
+        &self,
+        token: &str,
+        max_attempts: i32,
+    ) -> Result<Option<MfaChallenge>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let challenge = sqlx::query_as::<_, MfaChallenge>(
+            r#"
+            UPDATE mfa_challenges
+            SET failed_attempts = failed_attempts + 1
+            WHERE token_hash = $1 AND expires_at > NOW() AND failed_attempts < $2
+            RETURNING id, user_id, token_hash, expires_at, failed_attempts, created_at
+            "#,
+        )
+        .bind(hash_token(token))
+        .bind(max_attempts)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(challenge)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_user_challenges(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM mfa_challenges
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
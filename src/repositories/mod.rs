@@ -1,14 +1,36 @@
+mod article_repository;
+mod comment_repository;
+mod db;
+mod email_outbox_repository;
 mod email_verification_repository;
+mod mfa_repository;
+mod oauth_repository;
 mod password_reset_repository;
+mod profile_repository;
 mod refresh_token_repository;
+mod revoked_access_token_repository;
+mod tag_repository;
 mod traits;
+mod unit_of_work;
 mod user_repository;
 
+pub use article_repository::ArticleRepository;
+pub use comment_repository::CommentRepository;
+pub use email_outbox_repository::EmailOutboxRepository;
 pub use email_verification_repository::EmailVerificationRepository;
+pub use mfa_repository::MfaRepository;
+pub use oauth_repository::OAuthRepository;
 pub use password_reset_repository::PasswordResetRepository;
+pub use profile_repository::ProfileRepository;
 pub use refresh_token_repository::RefreshTokenRepository;
+pub use revoked_access_token_repository::RevokedAccessTokenRepository;
+pub use tag_repository::TagRepository;
 pub use traits::{
-    EmailVerificationRepositoryTrait, PasswordResetRepositoryTrait, RefreshTokenRepositoryTrait,
+    ArticleRepositoryTrait, CommentRepositoryTrait, EmailOutboxRepositoryTrait,
+    EmailVerificationRepositoryTrait, MfaRepositoryTrait, OAuthRepositoryTrait,
+    PasswordResetRepositoryTrait, ProfileRepositoryTrait, RefreshTokenRepositoryTrait,
+    RevokedAccessTokenRepositoryTrait, TagRepositoryTrait, UnitOfWorkFactoryTrait, UnitOfWorkTrait,
     UserRepositoryTrait,
 };
+pub use unit_of_work::{UnitOfWork, UnitOfWorkFactory};
 pub use user_repository::UserRepository;
@@ -0,0 +1,192 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::db::{Db, SharedTransaction};
+use super::traits::OAuthRepositoryTrait;
+use crate::auth::tokens::hash_token;
+use crate::models::{OAuthState, UserIdentity};
+
+#[derive(Clone)]
+pub struct OAuthRepository {
+    db: Db,
+}
+
+impl OAuthRepository {
+    pub fn new(db: PgPool) -> Self {
+        Self { db: Db::Pool(db) }
+    }
+
+    pub(super) fn in_transaction(tx: SharedTransaction) -> Self {
+        Self {
+            db: Db::Transaction(tx),
+        }
+    }
+}
+
+#[async_trait]
+impl OAuthRepositoryTrait for OAuthRepository {
+    #[instrument(skip(self, state, pkce_verifier, nonce))]
+    async fn create_state(
+        &self,
+        provider: &str,
+        state: &str,
+        pkce_verifier: &str,
+        nonce: Option<&str>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<OAuthState, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let oauth_state = sqlx::query_as::<_, OAuthState>(
+            r#"
+            INSERT INTO oauth_states (provider, state_hash, pkce_verifier, nonce, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, provider, state_hash, pkce_verifier, nonce, expires_at, created_at
+            "#,
+        )
+        .bind(provider)
+        .bind(hash_token(state))
+        .bind(pkce_verifier)
+        .bind(nonce)
+        .bind(expires_at)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(oauth_state)
+    }
+
+    #[instrument(skip(self, state))]
+    async fn consume_state(
+        &self,
+        provider: &str,
+        state: &str,
+    ) -> Result<Option<OAuthState>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let oauth_state = sqlx::query_as::<_, OAuthState>(
+            r#"
+            DELETE FROM oauth_states
+            WHERE provider = $1 AND state_hash = $2 AND expires_at > NOW()
+            RETURNING id, provider, state_hash, pkce_verifier, nonce, expires_at, created_at
+            "#,
+        )
+        .bind(provider)
+        .bind(hash_token(state))
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(oauth_state)
+    }
+
+    #[instrument(skip(self, code))]
+    async fn create_login_code(
+        &self,
+        user_id: Uuid,
+        code: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO oauth_login_codes (code_hash, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(hash_token(code))
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, code))]
+    async fn consume_login_code(&self, code: &str) -> Result<Option<Uuid>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let user_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            DELETE FROM oauth_login_codes
+            WHERE code_hash = $1 AND expires_at > NOW()
+            RETURNING user_id
+            "#,
+        )
+        .bind(hash_token(code))
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(user_id)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<UserIdentity>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let identity = sqlx::query_as::<_, UserIdentity>(
+            r#"
+            SELECT id, user_id, provider, subject, email, created_at, last_login_at
+            FROM user_identities
+            WHERE provider = $1 AND subject = $2
+            "#,
+        )
+        .bind(provider)
+        .bind(subject)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(identity)
+    }
+
+    #[instrument(skip(self, email))]
+    async fn create_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) -> Result<UserIdentity, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let identity = sqlx::query_as::<_, UserIdentity>(
+            r#"
+            INSERT INTO user_identities (user_id, provider, subject, email)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, user_id, provider, subject, email, created_at, last_login_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(provider)
+        .bind(subject)
+        .bind(email)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(identity)
+    }
+
+    #[instrument(skip(self))]
+    async fn record_login(&self, identity_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE user_identities
+            SET last_login_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(identity_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
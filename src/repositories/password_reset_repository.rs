@@ -4,71 +4,103 @@ use sqlx::PgPool;
 use tracing::instrument;
 use uuid::Uuid;
 
+use super::db::{Db, SharedTransaction};
 use super::traits::PasswordResetRepositoryTrait;
+use crate::auth::tokens::hash_token;
 use crate::models::PasswordResetToken;
 
 #[derive(Clone)]
 pub struct PasswordResetRepository {
-    db: PgPool,
+    db: Db,
 }
 
 impl PasswordResetRepository {
     pub fn new(db: PgPool) -> Self {
-        Self { db }
+        Self { db: Db::Pool(db) }
+    }
+
+    pub(super) fn in_transaction(tx: SharedTransaction) -> Self {
+        Self {
+            db: Db::Transaction(tx),
+        }
     }
 }
 
 #[async_trait]
 impl PasswordResetRepositoryTrait for PasswordResetRepository {
-    #[instrument(skip(self))]
+    #[instrument(skip(self, token))]
     async fn create_token(
         &self,
         user_id: Uuid,
         token: &str,
         expires_at: DateTime<Utc>,
     ) -> Result<PasswordResetToken, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let reset_token = sqlx::query_as::<_, PasswordResetToken>(
             r#"
-            INSERT INTO password_reset_tokens (user_id, token, expires_at)
+            INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
             VALUES ($1, $2, $3)
-            RETURNING id, user_id, token, expires_at, created_at
+            RETURNING id, user_id, token_hash, expires_at, created_at
             "#,
         )
         .bind(user_id)
-        .bind(token)
+        .bind(hash_token(token))
         .bind(expires_at)
-        .fetch_one(&self.db)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(reset_token)
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self, token))]
     async fn find_by_token(&self, token: &str) -> Result<Option<PasswordResetToken>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let reset_token = sqlx::query_as::<_, PasswordResetToken>(
             r#"
-            SELECT id, user_id, token, expires_at, created_at
+            SELECT id, user_id, token_hash, expires_at, created_at
             FROM password_reset_tokens
-            WHERE token = $1
+            WHERE token_hash = $1
             "#,
         )
-        .bind(token)
-        .fetch_optional(&self.db)
+        .bind(hash_token(token))
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(reset_token)
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self, token))]
+    async fn consume_token(&self, token: &str) -> Result<Option<Uuid>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let user_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            DELETE FROM password_reset_tokens
+            WHERE token_hash = $1 AND expires_at > NOW()
+            RETURNING user_id
+            "#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(user_id)
+    }
+
+    #[instrument(skip(self, token))]
     async fn delete_token(&self, token: &str) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         sqlx::query(
             r#"
             DELETE FROM password_reset_tokens
-            WHERE token = $1
+            WHERE token_hash = $1
             "#,
         )
-        .bind(token)
-        .execute(&self.db)
+        .bind(hash_token(token))
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
@@ -76,6 +108,8 @@ impl PasswordResetRepositoryTrait for PasswordResetRepository {
 
     #[instrument(skip(self))]
     async fn delete_all_user_tokens(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         sqlx::query(
             r#"
             DELETE FROM password_reset_tokens
@@ -83,7 +117,7 @@ impl PasswordResetRepositoryTrait for PasswordResetRepository {
             "#,
         )
         .bind(user_id)
-        .execute(&self.db)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
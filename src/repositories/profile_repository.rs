@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::traits::{ProfileRepositoryTrait, UserRepositoryTrait};
+use crate::models::Profile;
+
+#[derive(Clone)]
+pub struct ProfileRepository {
+    db: PgPool,
+    user_repository: Arc<dyn UserRepositoryTrait>,
+}
+
+impl ProfileRepository {
+    pub fn new(db: PgPool, user_repository: Arc<dyn UserRepositoryTrait>) -> Self {
+        Self {
+            db,
+            user_repository,
+        }
+    }
+}
+
+#[async_trait]
+impl ProfileRepositoryTrait for ProfileRepository {
+    #[instrument(skip(self))]
+    async fn find_by_username(
+        &self,
+        username: &str,
+        viewer_id: Option<Uuid>,
+    ) -> Result<Option<Profile>, sqlx::Error> {
+        let Some(user) = self.user_repository.find_by_username(username).await? else {
+            return Ok(None);
+        };
+
+        let following = match viewer_id {
+            Some(viewer_id) => self.is_following(viewer_id, user.id).await?,
+            None => false,
+        };
+
+        Ok(Some(Profile::from_user(user, following)))
+    }
+
+    #[instrument(skip(self))]
+    async fn is_following(
+        &self,
+        follower_id: Uuid,
+        followee_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let following = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM follows
+                WHERE follower_id = $1 AND followee_id = $2
+            )
+            "#,
+        )
+        .bind(follower_id)
+        .bind(followee_id)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(following)
+    }
+
+    #[instrument(skip(self))]
+    async fn follow(&self, follower_id: Uuid, followee_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO follows (follower_id, followee_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(follower_id)
+        .bind(followee_id)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn unfollow(&self, follower_id: Uuid, followee_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM follows
+            WHERE follower_id = $1 AND followee_id = $2
+            "#,
+        )
+        .bind(follower_id)
+        .bind(followee_id)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+}
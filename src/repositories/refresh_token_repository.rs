@@ -1,85 +1,153 @@
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use tracing::instrument;
 use uuid::Uuid;
 
+use super::db::{Db, SharedTransaction};
 use super::traits::RefreshTokenRepositoryTrait;
-use crate::models::RefreshToken;
+use crate::auth::tokens::hash_token;
+use crate::models::{ClientInfo, RefreshToken, Session};
 
 #[derive(Clone)]
 pub struct RefreshTokenRepository {
-    db: PgPool,
+    db: Db,
 }
 
 impl RefreshTokenRepository {
     pub fn new(db: PgPool) -> Self {
-        Self { db }
+        Self { db: Db::Pool(db) }
+    }
+
+    pub(super) fn in_transaction(tx: SharedTransaction) -> Self {
+        Self {
+            db: Db::Transaction(tx),
+        }
     }
 }
 
 #[async_trait]
 impl RefreshTokenRepositoryTrait for RefreshTokenRepository {
-    #[instrument(skip(self))]
-    async fn create_token(&self, user_id: Uuid, token: &str) -> Result<RefreshToken, sqlx::Error> {
+    #[instrument(skip(self, token, client))]
+    async fn create_token(
+        &self,
+        user_id: Uuid,
+        token: &str,
+        access_token_jti: Uuid,
+        access_token_expires_at: DateTime<Utc>,
+        client: &ClientInfo,
+    ) -> Result<RefreshToken, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let refresh_token = sqlx::query_as::<_, RefreshToken>(
             r#"
-            INSERT INTO refresh_tokens (user_id, token)
-            VALUES ($1, $2)
-            RETURNING id, user_id, token, expires_at, is_used, used_at, created_at, last_used_at
+            INSERT INTO refresh_tokens (user_id, token_hash, access_token_jti, access_token_expires_at,
+                                        user_agent, ip_address)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, user_id, token_hash, expires_at, is_used, used_at, created_at, last_used_at,
+                      family_id, parent_id, access_token_jti, access_token_expires_at,
+                      user_agent, ip_address
             "#,
         )
         .bind(user_id)
-        .bind(token)
-        .fetch_one(&self.db)
+        .bind(hash_token(token))
+        .bind(access_token_jti)
+        .bind(access_token_expires_at)
+        .bind(&client.user_agent)
+        .bind(&client.ip_address)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(refresh_token)
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self, parent, token, client), fields(family_id = %parent.family_id))]
+    async fn create_rotated_token(
+        &self,
+        parent: &RefreshToken,
+        token: &str,
+        access_token_jti: Uuid,
+        access_token_expires_at: DateTime<Utc>,
+        client: &ClientInfo,
+    ) -> Result<RefreshToken, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let refresh_token = sqlx::query_as::<_, RefreshToken>(
+            r#"
+            INSERT INTO refresh_tokens (user_id, token_hash, family_id, parent_id,
+                                        access_token_jti, access_token_expires_at,
+                                        user_agent, ip_address)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, user_id, token_hash, expires_at, is_used, used_at, created_at, last_used_at,
+                      family_id, parent_id, access_token_jti, access_token_expires_at,
+                      user_agent, ip_address
+            "#,
+        )
+        .bind(parent.user_id)
+        .bind(hash_token(token))
+        .bind(parent.family_id)
+        .bind(parent.id)
+        .bind(access_token_jti)
+        .bind(access_token_expires_at)
+        .bind(&client.user_agent)
+        .bind(&client.ip_address)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(refresh_token)
+    }
+
+    #[instrument(skip(self, token))]
     async fn find_by_token(&self, token: &str) -> Result<Option<RefreshToken>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let refresh_token = sqlx::query_as::<_, RefreshToken>(
             r#"
-            SELECT id, user_id, token, expires_at, is_used, used_at, created_at, last_used_at
+            SELECT id, user_id, token_hash, expires_at, is_used, used_at, created_at, last_used_at,
+                   family_id, parent_id, access_token_jti, access_token_expires_at,
+                      user_agent, ip_address
             FROM refresh_tokens
-            WHERE token = $1
+            WHERE token_hash = $1
             "#,
         )
-        .bind(token)
-        .fetch_optional(&self.db)
+        .bind(hash_token(token))
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(refresh_token)
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self, token))]
     async fn update_last_used(&self, token: &str) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         sqlx::query(
             r#"
             UPDATE refresh_tokens
             SET last_used_at = $1
-            WHERE token = $2
+            WHERE token_hash = $2
             "#,
         )
         .bind(Utc::now())
-        .bind(token)
-        .execute(&self.db)
+        .bind(hash_token(token))
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self, token))]
     async fn delete_token(&self, token: &str) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         sqlx::query(
             r#"
             DELETE FROM refresh_tokens
-            WHERE token = $1
+            WHERE token_hash = $1
             "#,
         )
-        .bind(token)
-        .execute(&self.db)
+        .bind(hash_token(token))
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
@@ -87,6 +155,8 @@ impl RefreshTokenRepositoryTrait for RefreshTokenRepository {
 
     #[instrument(skip(self))]
     async fn delete_all_user_tokens(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         sqlx::query(
             r#"
             DELETE FROM refresh_tokens
@@ -94,27 +164,162 @@ impl RefreshTokenRepositoryTrait for RefreshTokenRepository {
             "#,
         )
         .bind(user_id)
-        .execute(&self.db)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
     #[instrument(skip(self))]
-    async fn mark_token_as_used(&self, token: &str) -> Result<(), sqlx::Error> {
-        sqlx::query(
+    async fn revoke_family(&self, family_id: Uuid) -> Result<Vec<RefreshToken>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let revoked = sqlx::query_as::<_, RefreshToken>(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE family_id = $1
+            RETURNING id, user_id, token_hash, expires_at, is_used, used_at, created_at, last_used_at,
+                      family_id, parent_id, access_token_jti, access_token_expires_at,
+                      user_agent, ip_address
+            "#,
+        )
+        .bind(family_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(revoked)
+    }
+
+    #[instrument(skip(self, token))]
+    async fn consume_token(&self, token: &str) -> Result<Option<RefreshToken>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        // Concurrent updates of the same row are serialised and the loser re-checks the WHERE
+        // clause, so only one of them can flip `is_used`
+        let refresh_token = sqlx::query_as::<_, RefreshToken>(
             r#"
             UPDATE refresh_tokens
             SET is_used = TRUE,
-                used_at = $1
-            WHERE token = $2
+                used_at = $1,
+                last_used_at = $1
+            WHERE token_hash = $2
+              AND is_used = FALSE
+              AND expires_at > $1
+            RETURNING id, user_id, token_hash, expires_at, is_used, used_at, created_at, last_used_at,
+                      family_id, parent_id, access_token_jti, access_token_expires_at,
+                      user_agent, ip_address
             "#,
         )
         .bind(Utc::now())
-        .bind(token)
-        .execute(&self.db)
+        .bind(hash_token(token))
+        .fetch_optional(&mut *conn)
         .await?;
 
-        Ok(())
+        Ok(refresh_token)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_access_token_jti(
+        &self,
+        jti: Uuid,
+    ) -> Result<Option<RefreshToken>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let refresh_token = sqlx::query_as::<_, RefreshToken>(
+            r#"
+            SELECT id, user_id, token_hash, expires_at, is_used, used_at, created_at, last_used_at,
+                   family_id, parent_id, access_token_jti, access_token_expires_at,
+                      user_agent, ip_address
+            FROM refresh_tokens
+            WHERE access_token_jti = $1
+            "#,
+        )
+        .bind(jti)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(refresh_token)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_sessions(&self, user_id: Uuid) -> Result<Vec<Session>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        // A session is alive while the latest token of its family is unused and unexpired
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, user_agent, ip_address, started_at AS created_at,
+                   last_active_at AS last_used_at
+            FROM (
+                SELECT DISTINCT ON (family_id)
+                       family_id AS id, user_agent, ip_address, is_used, expires_at,
+                       MIN(created_at) OVER (PARTITION BY family_id) AS started_at,
+                       MAX(last_used_at) OVER (PARTITION BY family_id) AS last_active_at
+                FROM refresh_tokens
+                WHERE user_id = $1
+                ORDER BY family_id, created_at DESC
+            ) latest
+            WHERE NOT is_used
+              AND expires_at > NOW()
+            ORDER BY last_active_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke_session(
+        &self,
+        user_id: Uuid,
+        family_id: Uuid,
+    ) -> Result<Vec<RefreshToken>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let revoked = sqlx::query_as::<_, RefreshToken>(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE user_id = $1
+              AND family_id = $2
+            RETURNING id, user_id, token_hash, expires_at, is_used, used_at, created_at, last_used_at,
+                      family_id, parent_id, access_token_jti, access_token_expires_at,
+                      user_agent, ip_address
+            "#,
+        )
+        .bind(user_id)
+        .bind(family_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(revoked)
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke_other_sessions(
+        &self,
+        user_id: Uuid,
+        keep_family_id: Option<Uuid>,
+    ) -> Result<Vec<RefreshToken>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let revoked = sqlx::query_as::<_, RefreshToken>(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE user_id = $1
+              AND family_id IS DISTINCT FROM $2
+            RETURNING id, user_id, token_hash, expires_at, is_used, used_at, created_at, last_used_at,
+                      family_id, parent_id, access_token_jti, access_token_expires_at,
+                      user_agent, ip_address
+            "#,
+        )
+        .bind(user_id)
+        .bind(keep_family_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(revoked)
     }
 }
@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::traits::RevokedAccessTokenRepositoryTrait;
+use crate::models::RevokedAccessToken;
+
+#[derive(Clone)]
+pub struct RevokedAccessTokenRepository {
+    db: PgPool,
+}
+
+impl RevokedAccessTokenRepository {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl RevokedAccessTokenRepositoryTrait for RevokedAccessTokenRepository {
+    #[instrument(skip(self))]
+    async fn revoke(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO revoked_access_tokens (jti, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+        )
+        .bind(jti)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn find_active(&self) -> Result<Vec<RevokedAccessToken>, sqlx::Error> {
+        let revoked = sqlx::query_as::<_, RevokedAccessToken>(
+            r#"
+            SELECT jti, user_id, expires_at, revoked_at
+            FROM revoked_access_tokens
+            WHERE expires_at > NOW()
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(revoked)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_expired(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM revoked_access_tokens
+            WHERE expires_at <= NOW()
+            "#,
+        )
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use sqlx::{Connection, PgPool};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::db::{Db, SharedTransaction};
+use super::traits::TagRepositoryTrait;
+use crate::utils::normalize_tags;
+
+#[derive(Clone)]
+pub struct TagRepository {
+    db: Db,
+}
+
+impl TagRepository {
+    pub fn new(db: PgPool) -> Self {
+        Self { db: Db::Pool(db) }
+    }
+
+    pub(super) fn in_transaction(tx: SharedTransaction) -> Self {
+        Self {
+            db: Db::Transaction(tx),
+        }
+    }
+}
+
+#[async_trait]
+impl TagRepositoryTrait for TagRepository {
+    #[instrument(skip(self))]
+    async fn set_article_tags(
+        &self,
+        article_id: Uuid,
+        tags: &[String],
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let tags = normalize_tags(tags);
+
+        let mut conn = self.db.conn().await?;
+        // Only a savepoint inside a unit of work
+        let mut tx = conn.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO tags (name)
+            SELECT UNNEST($1::VARCHAR[])
+            ON CONFLICT (name) DO NOTHING
+            "#,
+        )
+        .bind(&tags)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM article_tags
+            WHERE article_id = $1
+            "#,
+        )
+        .bind(article_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO article_tags (article_id, tag_id)
+            SELECT $1, id FROM tags
+            WHERE name = ANY($2)
+            "#,
+        )
+        .bind(article_id)
+        .bind(&tags)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(tags)
+    }
+
+    #[instrument(skip(self))]
+    async fn popular(&self, limit: i64) -> Result<Vec<String>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let tags = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT t.name
+            FROM tags t
+            JOIN article_tags atg ON atg.tag_id = t.id
+            GROUP BY t.id, t.name
+            ORDER BY COUNT(*) DESC, t.name ASC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(tags)
+    }
+}
@@ -1,5 +1,11 @@
-use crate::models::{EmailVerificationToken, PasswordResetToken, RefreshToken, User};
+use crate::errors::Lang;
+use crate::models::{
+    Article, ArticleFilter, ArticleView, ClientInfo, Comment, CommentView, EmailVerificationToken,
+    MfaChallenge, OAuthState, OutboxEmail, OutboxMessage, PasswordResetToken, Profile,
+    RefreshToken, RevokedAccessToken, Session, User, UserIdentity, UserTotp,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use sqlx::Error as SqlxError;
 use uuid::Uuid;
 
@@ -10,21 +16,28 @@ pub trait UserRepositoryTrait: Send + Sync {
         username: &str,
         email: &str,
         password_hash: &str,
+        lang: Lang,
     ) -> Result<User, SqlxError>;
 
     async fn find_by_id(&self, user_id: Uuid) -> Result<Option<User>, SqlxError>;
 
     async fn find_by_email(&self, email: &str) -> Result<Option<User>, SqlxError>;
 
+    // Like `find_by_email`, for addresses that may differ from the registered one in case only
+    async fn find_by_email_ignoring_case(&self, email: &str) -> Result<Option<User>, SqlxError>;
+
     async fn find_by_username(&self, username: &str) -> Result<Option<User>, SqlxError>;
 
+    // Changing the email address resets `email_verified`
+    // `bio` and `image` are only changed if `Some`; `Some(None)` clears them
     async fn update(
         &self,
         id: Uuid,
         username: Option<&str>,
         email: Option<&str>,
-        bio: Option<&str>,
-        image: Option<&str>,
+        bio: Option<Option<&str>>,
+        image: Option<Option<&str>>,
+        lang: Option<Lang>,
     ) -> Result<Option<User>, SqlxError>;
 
     async fn update_password(
@@ -40,7 +53,7 @@ pub trait EmailVerificationRepositoryTrait: Send + Sync {
         &self,
         user_id: Uuid,
         token: &str,
-        expires_at: chrono::DateTime<chrono::Utc>,
+        expires_at: DateTime<Utc>,
     ) -> Result<EmailVerificationToken, SqlxError>;
 
     async fn find_by_token(&self, token: &str)
@@ -57,19 +70,68 @@ pub trait PasswordResetRepositoryTrait: Send + Sync {
         &self,
         user_id: Uuid,
         token: &str,
-        expires_at: chrono::DateTime<chrono::Utc>,
+        expires_at: DateTime<Utc>,
     ) -> Result<PasswordResetToken, SqlxError>;
 
     async fn find_by_token(&self, token: &str) -> Result<Option<PasswordResetToken>, SqlxError>;
 
+    // Deletes the token if it hasn't expired and returns its user, so it can only be used once
+    async fn consume_token(&self, token: &str) -> Result<Option<Uuid>, SqlxError>;
+
     async fn delete_token(&self, token: &str) -> Result<(), SqlxError>;
 
     async fn delete_all_user_tokens(&self, user_id: Uuid) -> Result<(), SqlxError>;
 }
 
+#[async_trait]
+pub trait EmailOutboxRepositoryTrait: Send + Sync {
+    async fn enqueue(&self, recipient: &str, email: &OutboxEmail) -> Result<Uuid, SqlxError>;
+
+    // Claims up to `limit` due messages and counts the attempt. Claimed messages stay hidden
+    // until `leased_until`, so messages of a worker that died mid-delivery are retried.
+    async fn claim_due(
+        &self,
+        limit: i64,
+        leased_until: DateTime<Utc>,
+    ) -> Result<Vec<OutboxMessage>, SqlxError>;
+
+    async fn mark_sent(&self, id: Uuid) -> Result<(), SqlxError>;
+
+    async fn reschedule(
+        &self,
+        id: Uuid,
+        next_attempt_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), SqlxError>;
+
+    // Dead messages are kept for inspection but never retried
+    async fn mark_dead(&self, id: Uuid, error: &str) -> Result<(), SqlxError>;
+
+    async fn count_pending(&self) -> Result<i64, SqlxError>;
+}
+
 #[async_trait]
 pub trait RefreshTokenRepositoryTrait: Send + Sync {
-    async fn create_token(&self, user_id: Uuid, token: &str) -> Result<RefreshToken, SqlxError>;
+    // Starts a new rotation family, i.e. a new session. The access token issued with the refresh
+    // token is recorded so it can be revoked along with the session.
+    async fn create_token(
+        &self,
+        user_id: Uuid,
+        token: &str,
+        access_token_jti: Uuid,
+        access_token_expires_at: DateTime<Utc>,
+        client: &ClientInfo,
+    ) -> Result<RefreshToken, SqlxError>;
+
+    // Adds the successor of `parent` to its family
+    async fn create_rotated_token(
+        &self,
+        parent: &RefreshToken,
+        token: &str,
+        access_token_jti: Uuid,
+        access_token_expires_at: DateTime<Utc>,
+        client: &ClientInfo,
+    ) -> Result<RefreshToken, SqlxError>;
 
     async fn find_by_token(&self, token: &str) -> Result<Option<RefreshToken>, SqlxError>;
 
@@ -79,5 +141,280 @@ pub trait RefreshTokenRepositoryTrait: Send + Sync {
 
     async fn delete_all_user_tokens(&self, user_id: Uuid) -> Result<(), SqlxError>;
 
-    async fn mark_token_as_used(&self, token: &str) -> Result<(), SqlxError>;
+    // Deletes every token of the family, returning the deleted tokens
+    async fn revoke_family(&self, family_id: Uuid) -> Result<Vec<RefreshToken>, SqlxError>;
+
+    // Marks the token as used if it is unused and unexpired, and returns it. Returns `None`
+    // otherwise, including when a concurrent request consumed it first.
+    async fn consume_token(&self, token: &str) -> Result<Option<RefreshToken>, SqlxError>;
+
+    // The token that was issued together with the access token `jti`
+    async fn find_by_access_token_jti(&self, jti: Uuid) -> Result<Option<RefreshToken>, SqlxError>;
+
+    // The user's live sessions, most recently used first
+    async fn list_sessions(&self, user_id: Uuid) -> Result<Vec<Session>, SqlxError>;
+
+    // Like `revoke_family`, but only if the family belongs to the user
+    async fn revoke_session(
+        &self,
+        user_id: Uuid,
+        family_id: Uuid,
+    ) -> Result<Vec<RefreshToken>, SqlxError>;
+
+    // Revokes every session of the user except `keep_family_id`, returning the deleted tokens
+    async fn revoke_other_sessions(
+        &self,
+        user_id: Uuid,
+        keep_family_id: Option<Uuid>,
+    ) -> Result<Vec<RefreshToken>, SqlxError>;
+}
+
+#[async_trait]
+pub trait RevokedAccessTokenRepositoryTrait: Send + Sync {
+    // Revoking a token twice is a no-op
+    async fn revoke(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), SqlxError>;
+
+    // Entries whose token hasn't expired yet
+    async fn find_active(&self) -> Result<Vec<RevokedAccessToken>, SqlxError>;
+
+    // Removes entries whose token has expired, returning how many were removed
+    async fn delete_expired(&self) -> Result<u64, SqlxError>;
+}
+
+#[async_trait]
+pub trait MfaRepositoryTrait: Send + Sync {
+    async fn find_totp(&self, user_id: Uuid) -> Result<Option<UserTotp>, SqlxError>;
+
+    // Stores a new pending authenticator, replacing a pending one; `None` if one is enabled
+    async fn start_enrollment(
+        &self,
+        user_id: Uuid,
+        secret_encrypted: &[u8],
+    ) -> Result<Option<UserTotp>, SqlxError>;
+
+    // Enables the pending authenticator, `step` being the one its first code was valid for
+    async fn enable_totp(&self, user_id: Uuid, step: i64) -> Result<bool, SqlxError>;
+
+    // Records a code's time step as used; `false` if it isn't later than the last one used
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, SqlxError>;
+
+    // Removes the authenticator together with the recovery codes
+    async fn delete_totp(&self, user_id: Uuid) -> Result<(), SqlxError>;
+
+    async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        codes: &[String],
+    ) -> Result<(), SqlxError>;
+
+    // Marks an unused recovery code as used; `false` if there is none
+    async fn use_recovery_code(&self, user_id: Uuid, code: &str) -> Result<bool, SqlxError>;
+
+    async fn count_unused_recovery_codes(&self, user_id: Uuid) -> Result<i64, SqlxError>;
+
+    async fn create_challenge(
+        &self,
+        user_id: Uuid,
+        token: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<MfaChallenge, SqlxError>;
+
+    // Deletes and returns the challenge if it hasn't expired, so it can only be completed once
+    async fn consume_challenge(&self, token: &str) -> Result<Option<MfaChallenge>, SqlxError>;
+
+    // Counts an attempt at the challenge before its code is checked; `None` if it has expired
+    // or had `max_attempts` already
+    async fn record_attempt(
+        &self,
+        token: &str,
+        max_attempts: i32,
+    ) -> Result<Option<MfaChallenge>, SqlxError>;
+
+    async fn delete_user_challenges(&self, user_id: Uuid) -> Result<(), SqlxError>;
+}
+
+#[async_trait]
+pub trait OAuthRepositoryTrait: Send + Sync {
+    async fn create_state(
+        &self,
+        provider: &str,
+        state: &str,
+        pkce_verifier: &str,
+        nonce: Option<&str>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<OAuthState, SqlxError>;
+
+    // Deletes and returns the provider's login with this state if it hasn't expired, so every
+    // callback can only be completed once
+    async fn consume_state(
+        &self,
+        provider: &str,
+        state: &str,
+    ) -> Result<Option<OAuthState>, SqlxError>;
+
+    async fn create_login_code(
+        &self,
+        user_id: Uuid,
+        code: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), SqlxError>;
+
+    // Deletes the code and returns its user if it hasn't expired, so it can only be used once
+    async fn consume_login_code(&self, code: &str) -> Result<Option<Uuid>, SqlxError>;
+
+    async fn find_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<UserIdentity>, SqlxError>;
+
+    async fn create_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) -> Result<UserIdentity, SqlxError>;
+
+    async fn record_login(&self, identity_id: Uuid) -> Result<(), SqlxError>;
+}
+
+#[async_trait]
+pub trait ArticleRepositoryTrait: Send + Sync {
+    // Returns `None` if the slug is already taken
+    async fn create(
+        &self,
+        author_id: Uuid,
+        slug: &str,
+        title: &str,
+        description: &str,
+        body: &str,
+    ) -> Result<Option<Article>, SqlxError>;
+
+    async fn find_by_slug(&self, slug: &str) -> Result<Option<Article>, SqlxError>;
+
+    async fn update(
+        &self,
+        id: Uuid,
+        slug: Option<&str>,
+        title: Option<&str>,
+        description: Option<&str>,
+        body: Option<&str>,
+    ) -> Result<Option<Article>, SqlxError>;
+
+    async fn delete(&self, id: Uuid) -> Result<(), SqlxError>;
+
+    // `viewer_id` personalises the `following` flag of each article's author
+    async fn find_view_by_slug(
+        &self,
+        slug: &str,
+        viewer_id: Option<Uuid>,
+    ) -> Result<Option<ArticleView>, SqlxError>;
+
+    // Returns one page of articles plus the total number of matching articles
+    async fn list(
+        &self,
+        filter: &ArticleFilter,
+        viewer_id: Option<Uuid>,
+    ) -> Result<(Vec<ArticleView>, i64), SqlxError>;
+
+    // Articles written by authors that `user_id` follows, newest first
+    async fn feed(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<ArticleView>, i64), SqlxError>;
+
+    // Both are idempotent and keep `favorites_count` in sync with the favorites table
+    async fn favorite(&self, article_id: Uuid, user_id: Uuid) -> Result<(), SqlxError>;
+
+    async fn unfavorite(&self, article_id: Uuid, user_id: Uuid) -> Result<(), SqlxError>;
+}
+
+#[async_trait]
+pub trait ProfileRepositoryTrait: Send + Sync {
+    // `viewer_id` decides the `following` flag; anonymous viewers never follow anyone
+    async fn find_by_username(
+        &self,
+        username: &str,
+        viewer_id: Option<Uuid>,
+    ) -> Result<Option<Profile>, SqlxError>;
+
+    async fn is_following(&self, follower_id: Uuid, followee_id: Uuid) -> Result<bool, SqlxError>;
+
+    async fn follow(&self, follower_id: Uuid, followee_id: Uuid) -> Result<(), SqlxError>;
+
+    async fn unfollow(&self, follower_id: Uuid, followee_id: Uuid) -> Result<(), SqlxError>;
+}
+
+#[async_trait]
+pub trait CommentRepositoryTrait: Send + Sync {
+    async fn create(
+        &self,
+        article_id: Uuid,
+        author_id: Uuid,
+        body: &str,
+    ) -> Result<Comment, SqlxError>;
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Comment>, SqlxError>;
+
+    // Oldest first; `viewer_id` personalises the `following` flag of each comment's author
+    async fn list_for_article(
+        &self,
+        article_id: Uuid,
+        viewer_id: Option<Uuid>,
+    ) -> Result<Vec<CommentView>, SqlxError>;
+
+    async fn delete(&self, id: Uuid) -> Result<(), SqlxError>;
+}
+
+#[async_trait]
+pub trait TagRepositoryTrait: Send + Sync {
+    // Replaces the article's tags; tags are trimmed, lowercased and de-duplicated first.
+    // Returns the normalised tag list that was stored.
+    async fn set_article_tags(
+        &self,
+        article_id: Uuid,
+        tags: &[String],
+    ) -> Result<Vec<String>, SqlxError>;
+
+    // Tag names ordered by how many articles use them
+    async fn popular(&self, limit: i64) -> Result<Vec<String>, SqlxError>;
+}
+
+/// Starts units of work, see [`UnitOfWorkTrait`].
+#[async_trait]
+pub trait UnitOfWorkFactoryTrait: Send + Sync {
+    async fn begin(&self) -> Result<Box<dyn UnitOfWorkTrait>, SqlxError>;
+}
+
+/// Repositories sharing one database transaction, so a handler can make several changes
+/// atomically. Dropping the unit of work without committing rolls the changes back.
+#[async_trait]
+pub trait UnitOfWorkTrait: Send + Sync {
+    fn users(&self) -> &dyn UserRepositoryTrait;
+
+    fn email_verifications(&self) -> &dyn EmailVerificationRepositoryTrait;
+
+    fn password_resets(&self) -> &dyn PasswordResetRepositoryTrait;
+
+    fn refresh_tokens(&self) -> &dyn RefreshTokenRepositoryTrait;
+
+    fn email_outbox(&self) -> &dyn EmailOutboxRepositoryTrait;
+
+    fn mfa(&self) -> &dyn MfaRepositoryTrait;
+
+    fn oauth(&self) -> &dyn OAuthRepositoryTrait;
+
+    fn articles(&self) -> &dyn ArticleRepositoryTrait;
+
+    fn tags(&self) -> &dyn TagRepositoryTrait;
+
+    async fn commit(self: Box<Self>) -> Result<(), SqlxError>;
 }
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+use super::db::SharedTransaction;
+use super::traits::{
+    ArticleRepositoryTrait, EmailOutboxRepositoryTrait, EmailVerificationRepositoryTrait,
+    MfaRepositoryTrait, OAuthRepositoryTrait, PasswordResetRepositoryTrait,
+    RefreshTokenRepositoryTrait, TagRepositoryTrait, UnitOfWorkFactoryTrait, UnitOfWorkTrait,
+    UserRepositoryTrait,
+};
+use super::{
+    ArticleRepository, EmailOutboxRepository, EmailVerificationRepository, MfaRepository,
+    OAuthRepository, PasswordResetRepository, RefreshTokenRepository, TagRepository,
+    UserRepository,
+};
+
+#[derive(Clone)]
+pub struct UnitOfWorkFactory {
+    db: PgPool,
+}
+
+impl UnitOfWorkFactory {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkFactoryTrait for UnitOfWorkFactory {
+    #[instrument(skip(self))]
+    async fn begin(&self) -> Result<Box<dyn UnitOfWorkTrait>, sqlx::Error> {
+        let tx: SharedTransaction = Arc::new(Mutex::new(Some(self.db.begin().await?)));
+
+        Ok(Box::new(UnitOfWork {
+            users: UserRepository::in_transaction(tx.clone()),
+            email_verifications: EmailVerificationRepository::in_transaction(tx.clone()),
+            password_resets: PasswordResetRepository::in_transaction(tx.clone()),
+            refresh_tokens: RefreshTokenRepository::in_transaction(tx.clone()),
+            email_outbox: EmailOutboxRepository::in_transaction(tx.clone()),
+            mfa: MfaRepository::in_transaction(tx.clone()),
+            oauth: OAuthRepository::in_transaction(tx.clone()),
+            articles: ArticleRepository::in_transaction(tx.clone()),
+            tags: TagRepository::in_transaction(tx.clone()),
+            tx,
+        }))
+    }
+}
+
+/// Repositories running their queries in one shared transaction.
+///
+/// Nothing is visible to other connections until [`UnitOfWorkTrait::commit`]; dropping the unit
+/// of work instead, e.g. when a handler returns early with `?`, rolls everything back.
+pub struct UnitOfWork {
+    tx: SharedTransaction,
+    users: UserRepository,
+    email_verifications: EmailVerificationRepository,
+    password_resets: PasswordResetRepository,
+    refresh_tokens: RefreshTokenRepository,
+    email_outbox: EmailOutboxRepository,
+    mfa: MfaRepository,
+    oauth: OAuthRepository,
+    articles: ArticleRepository,
+    tags: TagRepository,
+}
+
+#[async_trait]
+impl UnitOfWorkTrait for UnitOfWork {
+    fn users(&self) -> &dyn UserRepositoryTrait {
+        &self.users
+    }
+
+    fn email_verifications(&self) -> &dyn EmailVerificationRepositoryTrait {
+        &self.email_verifications
+    }
+
+    fn password_resets(&self) -> &dyn PasswordResetRepositoryTrait {
+        &self.password_resets
+    }
+
+    fn refresh_tokens(&self) -> &dyn RefreshTokenRepositoryTrait {
+        &self.refresh_tokens
+    }
+
+    fn email_outbox(&self) -> &dyn EmailOutboxRepositoryTrait {
+        &self.email_outbox
+    }
+
+    fn mfa(&self) -> &dyn MfaRepositoryTrait {
+        &self.mfa
+    }
+
+    fn oauth(&self) -> &dyn OAuthRepositoryTrait {
+        &self.oauth
+    }
+
+    fn articles(&self) -> &dyn ArticleRepositoryTrait {
+        &self.articles
+    }
+
+    fn tags(&self) -> &dyn TagRepositoryTrait {
+        &self.tags
+    }
+
+    #[instrument(skip(self))]
+    async fn commit(self: Box<Self>) -> Result<(), sqlx::Error> {
+        let tx = self.tx.lock().await.take();
+        match tx {
+            Some(tx) => tx.commit().await,
+            None => Err(sqlx::Error::Protocol(
+                "unit of work has already been committed".to_string(),
+            )),
+        }
+    }
+}
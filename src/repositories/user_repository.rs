@@ -1,19 +1,26 @@
-use crate::models::User;
+use crate::{errors::Lang, models::User};
 use async_trait::async_trait;
 use sqlx::PgPool;
 use tracing::instrument;
 use uuid::Uuid;
 
+use super::db::{Db, SharedTransaction};
 use super::traits::UserRepositoryTrait;
 
 #[derive(Clone)]
 pub struct UserRepository {
-    db: PgPool,
+    db: Db,
 }
 
 impl UserRepository {
     pub fn new(db: PgPool) -> Self {
-        Self { db }
+        Self { db: Db::Pool(db) }
+    }
+
+    pub(super) fn in_transaction(tx: SharedTransaction) -> Self {
+        Self {
+            db: Db::Transaction(tx),
+        }
     }
 }
 
@@ -25,19 +32,23 @@ impl UserRepositoryTrait for UserRepository {
         username: &str,
         email: &str,
         password_hash: &str,
+        lang: Lang,
     ) -> Result<User, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let user = sqlx::query_as::<_, User>(
             r#"
-            INSERT INTO users (username, email, password_hash)
-            VALUES ($1, $2, $3)
+            INSERT INTO users (username, email, password_hash, lang)
+            VALUES ($1, $2, $3, $4)
             RETURNING id, username, email, password_hash, bio, image,
-                      email_verified, created_at, updated_at
+                      email_verified, lang, created_at, updated_at
             "#,
         )
         .bind(username)
         .bind(email)
         .bind(password_hash)
-        .fetch_one(&self.db)
+        .bind(lang)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(user)
@@ -45,16 +56,18 @@ impl UserRepositoryTrait for UserRepository {
 
     #[instrument(skip(self))]
     async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let user = sqlx::query_as::<_, User>(
             r#"
             SELECT id, username, email, password_hash, bio, image,
-                   email_verified, created_at, updated_at
+                   email_verified, lang, created_at, updated_at
             FROM users
             WHERE id = $1
             "#,
         )
         .bind(id)
-        .fetch_optional(&self.db)
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(user)
@@ -62,16 +75,37 @@ impl UserRepositoryTrait for UserRepository {
 
     #[instrument(skip(self))]
     async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let user = sqlx::query_as::<_, User>(
             r#"
             SELECT id, username, email, password_hash, bio, image,
-                   email_verified, created_at, updated_at
+                   email_verified, lang, created_at, updated_at
             FROM users
             WHERE email = $1
             "#,
         )
         .bind(email)
-        .fetch_optional(&self.db)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(user)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_email_ignoring_case(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            SELECT id, username, email, password_hash, bio, image,
+                   email_verified, lang, created_at, updated_at
+            FROM users
+            WHERE lower(email) = lower($1)
+            "#,
+        )
+        .bind(email)
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(user)
@@ -79,16 +113,18 @@ impl UserRepositoryTrait for UserRepository {
 
     #[instrument(skip(self))]
     async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let user = sqlx::query_as::<_, User>(
             r#"
             SELECT id, username, email, password_hash, bio, image,
-                   created_at, updated_at
+                   email_verified, lang, created_at, updated_at
             FROM users
             WHERE username = $1
             "#,
         )
         .bind(username)
-        .fetch_optional(&self.db)
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(user)
@@ -100,27 +136,38 @@ impl UserRepositoryTrait for UserRepository {
         id: Uuid,
         username: Option<&str>,
         email: Option<&str>,
-        bio: Option<&str>,
-        image: Option<&str>,
+        bio: Option<Option<&str>>,
+        image: Option<Option<&str>>,
+        lang: Option<Lang>,
     ) -> Result<Option<User>, sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         let user = sqlx::query_as::<_, User>(
             r#"
             UPDATE users
             SET username = COALESCE($2, username),
                 email = COALESCE($3, email),
-                bio = COALESCE($4, bio),
-                image = COALESCE($5, image)
+                email_verified = CASE
+                    WHEN $3 IS NOT NULL AND $3 <> email THEN FALSE
+                    ELSE email_verified
+                END,
+                bio = CASE WHEN $4 THEN $5 ELSE bio END,
+                image = CASE WHEN $6 THEN $7 ELSE image END,
+                lang = COALESCE($8, lang)
             WHERE id = $1
             RETURNING id, username, email, password_hash, bio, image,
-                      created_at, updated_at
+                      email_verified, lang, created_at, updated_at
             "#,
         )
         .bind(id)
         .bind(username)
         .bind(email)
-        .bind(bio)
-        .bind(image)
-        .fetch_optional(&self.db)
+        .bind(bio.is_some())
+        .bind(bio.flatten())
+        .bind(image.is_some())
+        .bind(image.flatten())
+        .bind(lang)
+        .fetch_optional(&mut *conn)
         .await?;
 
         Ok(user)
@@ -132,6 +179,8 @@ impl UserRepositoryTrait for UserRepository {
         user_id: Uuid,
         new_password_hash: &str,
     ) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.conn().await?;
+
         sqlx::query(
             r#"
                 UPDATE users
@@ -141,7 +190,7 @@ impl UserRepositoryTrait for UserRepository {
         )
         .bind(user_id)
         .bind(new_password_hash)
-        .execute(&self.db)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::profile_schemas::ProfileData;
+use crate::{
+    models::{ArticleFilter, ArticleView},
+    utils::validate_tags,
+};
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct ListArticlesQuery {
+    pub tag: Option<String>,
+    pub author: Option<String>,
+    pub favorited: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl ListArticlesQuery {
+    pub fn into_filter(self) -> ArticleFilter {
+        ArticleFilter {
+            tag: self.tag,
+            author: self.author,
+            favorited: self.favorited,
+            limit: clamp_limit(self.limit),
+            offset: clamp_offset(self.offset),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl FeedQuery {
+    pub fn limit(&self) -> i64 {
+        clamp_limit(self.limit)
+    }
+
+    pub fn offset(&self) -> i64 {
+        clamp_offset(self.offset)
+    }
+}
+
+fn clamp_limit(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+fn clamp_offset(offset: Option<i64>) -> i64 {
+    offset.unwrap_or(0).max(0)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateArticleRequest {
+    pub article: CreateArticleData,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateArticleData {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Title must be between 1 and 255 characters"
+    ))]
+    pub title: String,
+
+    #[validate(length(min = 1, message = "Description is required"))]
+    pub description: String,
+
+    #[validate(length(min = 1, message = "Body is required"))]
+    pub body: String,
+
+    #[serde(default, rename = "tagList")]
+    #[validate(custom(function = "validate_tags"))]
+    pub tag_list: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateArticleRequest {
+    pub article: UpdateArticleData,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateArticleData {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Title must be between 1 and 255 characters"
+    ))]
+    pub title: Option<String>,
+
+    #[validate(length(min = 1, message = "Description cannot be empty"))]
+    pub description: Option<String>,
+
+    #[validate(length(min = 1, message = "Body cannot be empty"))]
+    pub body: Option<String>,
+
+    #[serde(rename = "tagList")]
+    #[validate(custom(function = "validate_tags"))]
+    pub tag_list: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArticleResponse {
+    pub article: ArticleData,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultipleArticlesResponse {
+    pub articles: Vec<ArticleData>,
+    pub articles_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticleData {
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub tag_list: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub favorited: bool,
+    pub favorites_count: i32,
+    pub author: ProfileData,
+}
+
+impl ArticleData {
+    pub fn from_view(view: ArticleView) -> Self {
+        let article = view.article;
+        Self {
+            slug: article.slug,
+            title: article.title,
+            description: article.description,
+            body: article.body,
+            tag_list: view.tag_list,
+            created_at: article.created_at,
+            updated_at: article.updated_at,
+            favorited: view.favorited,
+            favorites_count: article.favorites_count,
+            author: ProfileData {
+                username: view.author_username,
+                bio: view.author_bio,
+                image: view.author_image,
+                following: view.following,
+            },
+        }
+    }
+}
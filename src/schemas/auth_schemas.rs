@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
+use super::MfaRequiredResponse;
+use crate::errors::Lang;
+
 #[derive(Debug, Deserialize)]
 pub struct RegisterUserRequest {
     pub user: RegisterUserData,
@@ -20,6 +23,10 @@ pub struct RegisterUserData {
 
     #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
     pub password: String,
+
+    // Language of the emails we send, English unless given
+    #[serde(default)]
+    pub lang: Lang,
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,6 +50,14 @@ pub struct LoginResponse {
     pub refresh_token: String,
 }
 
+// Login either finishes right away or waits for the second factor
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResult {
+    Authenticated(LoginResponse),
+    MfaRequired(MfaRequiredResponse),
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
     pub user: UserData,
@@ -55,6 +70,7 @@ pub struct UserData {
     pub bio: String,
     pub image: Option<String>,
     pub email_verified: bool,
+    pub lang: Lang,
 }
 
 impl UserData {
@@ -65,6 +81,7 @@ impl UserData {
             bio: user.bio.unwrap_or_default(),
             image: user.image,
             email_verified: user.email_verified,
+            lang: user.lang,
         }
     }
 }
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::profile_schemas::ProfileData;
+use crate::models::{Comment, CommentView, User};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCommentRequest {
+    pub comment: CreateCommentData,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCommentData {
+    #[validate(length(min = 1, message = "Comment body is required"))]
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommentResponse {
+    pub comment: CommentData,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MultipleCommentsResponse {
+    pub comments: Vec<CommentData>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentData {
+    pub id: Uuid,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub author: ProfileData,
+}
+
+impl CommentData {
+    pub fn from_comment(comment: Comment, author: User) -> Self {
+        Self {
+            id: comment.id,
+            body: comment.body,
+            created_at: comment.created_at,
+            updated_at: comment.updated_at,
+            author: ProfileData::from_user(author),
+        }
+    }
+
+    pub fn from_view(view: CommentView) -> Self {
+        let comment = view.comment;
+        Self {
+            id: comment.id,
+            body: comment.body,
+            created_at: comment.created_at,
+            updated_at: comment.updated_at,
+            author: ProfileData {
+                username: view.author_username,
+                bio: view.author_bio,
+                image: view.author_image,
+                following: view.following,
+            },
+        }
+    }
+}
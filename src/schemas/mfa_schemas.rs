@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Returned by login instead of tokens when the user has two-factor authentication enabled
+#[derive(Debug, Serialize)]
+pub struct MfaRequiredResponse {
+    pub mfa_required: bool,
+    // Completes the login together with a code at /api/users/login/mfa
+    pub mfa_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyMfaLoginRequest {
+    pub mfa_token: String,
+    // A code from the authenticator app or a recovery code
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaStatusResponse {
+    pub enabled: bool,
+    pub recovery_codes_remaining: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnrollMfaRequest {
+    pub password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaEnrollmentResponse {
+    // Base32, for entering the secret by hand
+    pub secret: String,
+    // For authenticator apps, usually rendered as a QR code
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableMfaRequest {
+    pub password: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DisableMfaResponse {
+    pub message: String,
+}
+
+// Shown once; only their digests are stored
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
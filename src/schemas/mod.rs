@@ -1,8 +1,24 @@
+pub mod article_schemas;
 pub mod auth_schemas;
+pub mod comment_schemas;
+pub mod mfa_schemas;
+pub mod oauth_schemas;
 pub mod password_reset_schemas;
+pub mod profile_schemas;
+pub mod session_schemas;
+pub mod tag_schemas;
 pub mod token_schemas;
 pub mod user_schemas;
 
+pub use article_schemas::*;
 pub use auth_schemas::*;
+pub use comment_schemas::*;
+pub use mfa_schemas::*;
+pub use oauth_schemas::*;
+pub use profile_schemas::*;
+pub use session_schemas::*;
+pub use tag_schemas::*;
 pub use token_schemas::*;
-pub use user_schemas::{CreateUserRequest, UpdateUserRequest, UserResponse};
+pub use user_schemas::{
+    CreateUserRequest, UpdateCurrentUserRequest, UpdateUserRequest, UserResponse,
+};
@@ -0,0 +1,16 @@
+use serde::Deserialize;
+
+// Where the identity provider sends the user back to, see RFC 6749 4.1.2
+#[derive(Debug, Deserialize)]
+pub struct OAuthCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    // Set instead of `code` if the user declined or the login failed at the provider
+    pub error: Option<String>,
+}
+
+// Trades the one-time code from the callback redirect for the tokens
+#[derive(Debug, Deserialize)]
+pub struct OAuthExchangeRequest {
+    pub code: String,
+}
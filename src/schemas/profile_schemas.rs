@@ -0,0 +1,36 @@
+use serde::Serialize;
+
+use crate::models::{Profile, User};
+
+#[derive(Debug, Serialize)]
+pub struct ProfileResponse {
+    pub profile: ProfileData,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileData {
+    pub username: String,
+    pub bio: Option<String>,
+    pub image: Option<String>,
+    pub following: bool,
+}
+
+impl ProfileData {
+    pub fn from_user(user: User) -> Self {
+        Self {
+            username: user.username,
+            bio: user.bio,
+            image: user.image,
+            following: false,
+        }
+    }
+
+    pub fn from_profile(profile: Profile) -> Self {
+        Self {
+            username: profile.username,
+            bio: profile.bio,
+            image: profile.image,
+            following: profile.following,
+        }
+    }
+}
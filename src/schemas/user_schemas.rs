@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::errors::Lang;
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateUserRequest {
     #[validate(length(
@@ -31,11 +33,27 @@ pub struct UpdateUserRequest {
     #[validate(email(message = "Invalid email format"))]
     pub email: Option<String>,
 
+    // `null` clears the bio, leaving it out keeps it
+    #[serde(default, with = "::serde_with::rust::double_option")]
     #[validate(length(max = 500, message = "Bio cannot exceed 500 characters"))]
-    pub bio: Option<String>,
+    pub bio: Option<Option<String>>,
 
+    #[serde(default, with = "::serde_with::rust::double_option")]
     #[validate(url(message = "Image must be a valid URL"))]
-    pub image: Option<String>,
+    pub image: Option<Option<String>>,
+
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub password: Option<String>,
+
+    // Required when changing the password
+    pub current_password: Option<String>,
+
+    pub lang: Option<Lang>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCurrentUserRequest {
+    pub user: UpdateUserRequest,
 }
 
 #[derive(Debug, Serialize)]
@@ -62,4 +80,3 @@ impl From<crate::models::User> for UserResponse {
         }
     }
 }
-
@@ -0,0 +1,112 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use tracing::{error, info, instrument};
+use uuid::Uuid;
+
+use crate::{models::RefreshToken, repositories::RevokedAccessTokenRepositoryTrait};
+
+/// Access tokens that were revoked before their expiry, checked on every authenticated request.
+///
+/// The list is kept in Postgres so all instances share it. Each instance holds the unexpired
+/// entries in memory and merges in the stored ones every sync interval, so a token revoked on
+/// another instance is rejected here too after at most one interval.
+pub struct AccessTokenDenylist {
+    repository: Arc<dyn RevokedAccessTokenRepositoryTrait>,
+    // jti -> expiry of the revoked token
+    revoked: RwLock<HashMap<Uuid, DateTime<Utc>>>,
+}
+
+impl AccessTokenDenylist {
+    pub fn new(repository: Arc<dyn RevokedAccessTokenRepositoryTrait>) -> Self {
+        Self {
+            repository,
+            revoked: RwLock::new(HashMap::new()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn revoke(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        // An expired token is rejected anyway
+        if expires_at <= Utc::now() {
+            return Ok(());
+        }
+
+        self.repository.revoke(jti, user_id, expires_at).await?;
+        self.revoked
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(jti, expires_at);
+
+        Ok(())
+    }
+
+    /// Revokes the access tokens that were issued together with the given refresh tokens, e.g.
+    /// the ones of a session that was just ended.
+    pub async fn revoke_issued_with(
+        &self,
+        refresh_tokens: &[RefreshToken],
+    ) -> Result<(), sqlx::Error> {
+        for refresh_token in refresh_tokens {
+            if let (Some(jti), Some(expires_at)) = (
+                refresh_token.access_token_jti,
+                refresh_token.access_token_expires_at,
+            ) {
+                self.revoke(jti, refresh_token.user_id, expires_at).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_revoked(&self, jti: &Uuid) -> bool {
+        self.revoked
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(jti)
+    }
+
+    /// Prunes expired entries and picks up tokens revoked by other instances.
+    pub async fn sync(&self) -> Result<(), sqlx::Error> {
+        self.repository.delete_expired().await?;
+        let stored = self.repository.find_active().await?;
+
+        // Merge instead of replacing, so a token revoked here while the query ran isn't lost;
+        // revocations are never undone, entries only go away once they expire
+        let now = Utc::now();
+        let mut revoked = self.revoked.write().unwrap_or_else(PoisonError::into_inner);
+        revoked.retain(|_, expires_at| *expires_at > now);
+        revoked.extend(
+            stored
+                .into_iter()
+                .map(|entry| (entry.jti, entry.expires_at)),
+        );
+
+        Ok(())
+    }
+
+    pub fn spawn_sync(self: Arc<Self>, sync_interval: Duration) -> JoinHandle<()> {
+        info!("Starting access token denylist sync...");
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(sync_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                if let Err(err) = self.sync().await {
+                    error!("Failed to sync access token denylist: {}", err);
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,143 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use tracing::{error, info, instrument, warn};
+
+use super::EmailService;
+use crate::{
+    config::OutboxConfig, metrics::Metrics, models::OutboxMessage,
+    repositories::EmailOutboxRepositoryTrait,
+};
+
+// How long a claimed batch stays hidden from other workers; must outlast the SMTP timeouts of
+// a whole batch, otherwise messages could be sent twice
+const CLAIM_LEASE: Duration = Duration::from_secs(5 * 60);
+
+/// Delivers queued emails in the background, retrying failures with exponential backoff and
+/// dead-lettering messages that keep failing.
+pub struct EmailOutboxWorker {
+    repository: Arc<dyn EmailOutboxRepositoryTrait>,
+    email_service: Arc<EmailService>,
+    config: OutboxConfig,
+    metrics: Option<Metrics>,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(
+        repository: Arc<dyn EmailOutboxRepositoryTrait>,
+        email_service: Arc<EmailService>,
+        config: OutboxConfig,
+        metrics: Option<Metrics>,
+    ) -> Self {
+        Self {
+            repository,
+            email_service,
+            config,
+            metrics,
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        info!("Starting email outbox worker...");
+        tokio::spawn(self.run())
+    }
+
+    async fn run(self) {
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.drain().await {
+                error!("Failed to process email outbox: {}", err);
+            }
+        }
+    }
+
+    // Delivers due messages batch by batch until none are left
+    async fn drain(&self) -> Result<(), sqlx::Error> {
+        loop {
+            let leased_until = Utc::now() + CLAIM_LEASE;
+            let messages = self
+                .repository
+                .claim_due(self.config.batch_size, leased_until)
+                .await?;
+            let claimed = messages.len();
+
+            for message in messages {
+                self.deliver(message).await?;
+            }
+
+            if (claimed as i64) < self.config.batch_size {
+                break;
+            }
+        }
+
+        if let Some(metrics) = &self.metrics {
+            let pending = self.repository.count_pending().await?;
+            metrics.record_email_outbox_pending(pending as u64);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, message), fields(id = %message.id, kind = message.email.kind()))]
+    async fn deliver(&self, message: OutboxMessage) -> Result<(), sqlx::Error> {
+        let kind = message.email.kind();
+        let result = self
+            .email_service
+            .deliver(&message.recipient, &message.email)
+            .await
+            .map_err(|err| err.to_string());
+
+        let err = match result {
+            Ok(()) => {
+                self.repository.mark_sent(message.id).await?;
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_email_sent(kind);
+                }
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_email_delivery_failure(kind);
+        }
+
+        let attempts = message.attempts as u32;
+        if attempts >= self.config.max_attempts {
+            error!(
+                "Giving up on {} email after {} attempts: {}",
+                kind, attempts, err
+            );
+            self.repository.mark_dead(message.id, &err).await?;
+            if let Some(metrics) = &self.metrics {
+                metrics.record_email_dead_lettered(kind);
+            }
+        } else {
+            let delay = backoff(&self.config, attempts);
+            warn!(
+                "Failed to deliver {} email (attempt {}), retrying in {:?}: {}",
+                kind, attempts, delay, err
+            );
+            self.repository
+                .reschedule(message.id, Utc::now() + delay, &err)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+// base, 2 * base, 4 * base, ... capped at `backoff_max`
+fn backoff(config: &OutboxConfig, attempts: u32) -> Duration {
+    let factor = 1u32
+        .checked_shl(attempts.saturating_sub(1))
+        .unwrap_or(u32::MAX);
+    config
+        .backoff_base
+        .saturating_mul(factor)
+        .min(config.backoff_max)
+}
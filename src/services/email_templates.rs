@@ -0,0 +1,96 @@
+//! Askama templates of the emails we send. Every email has an HTML and a plain-text variant
+//! sharing the same fields, both extending the layouts in `templates/emails/`.
+
+use askama::Template;
+
+use crate::errors::Lang;
+
+#[derive(Template)]
+#[template(path = "emails/verification.html.askama")]
+pub struct VerificationHtml<'a> {
+    pub lang: Lang,
+    pub app_name: &'a str,
+    pub subject: &'a str,
+    pub username: &'a str,
+    pub link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/verification.txt.askama")]
+pub struct VerificationText<'a> {
+    pub lang: Lang,
+    pub app_name: &'a str,
+    pub subject: &'a str,
+    pub username: &'a str,
+    pub link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.html.askama")]
+pub struct PasswordResetHtml<'a> {
+    pub lang: Lang,
+    pub app_name: &'a str,
+    pub subject: &'a str,
+    pub username: &'a str,
+    pub link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.txt.askama")]
+pub struct PasswordResetText<'a> {
+    pub lang: Lang,
+    pub app_name: &'a str,
+    pub subject: &'a str,
+    pub username: &'a str,
+    pub link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/security_alert.html.askama")]
+pub struct SecurityAlertHtml<'a> {
+    pub lang: Lang,
+    pub app_name: &'a str,
+    pub subject: &'a str,
+    pub username: &'a str,
+    // Login time of the revoked session, already formatted
+    pub session_started_at: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+    pub ip_address: Option<&'a str>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/security_alert.txt.askama")]
+pub struct SecurityAlertText<'a> {
+    pub lang: Lang,
+    pub app_name: &'a str,
+    pub subject: &'a str,
+    pub username: &'a str,
+    // Login time of the revoked session, already formatted
+    pub session_started_at: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+    pub ip_address: Option<&'a str>,
+}
+
+pub fn verification_subject(lang: Lang) -> &'static str {
+    match lang {
+        Lang::en => "Verify Your Email Address",
+        Lang::de => "Bestätige deine E-Mail-Adresse",
+        Lang::fr => "Confirmez votre adresse e-mail",
+    }
+}
+
+pub fn password_reset_subject(lang: Lang) -> &'static str {
+    match lang {
+        Lang::en => "Reset Your Password",
+        Lang::de => "Setze dein Passwort zurück",
+        Lang::fr => "Réinitialisez votre mot de passe",
+    }
+}
+
+pub fn security_alert_subject(lang: Lang) -> &'static str {
+    match lang {
+        Lang::en => "Security Alert: Suspicious Activity Detected",
+        Lang::de => "Sicherheitswarnung: Verdächtige Aktivität erkannt",
+        Lang::fr => "Alerte de sécurité : activité suspecte détectée",
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    transport::smtp::{PoolConfig, authentication::Credentials},
+};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::config::{EmailTransportConfig, SmtpConfig};
+
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum EmailTransportError {
+    /// SMTP delivery failed: {0}
+    Smtp(#[from] lettre::transport::smtp::Error),
+    /// could not write email file: {0}
+    Io(#[from] std::io::Error),
+}
+
+/// Delivers fully built messages; `EmailService` composes them.
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, message: &Message) -> Result<(), EmailTransportError>;
+}
+
+/// Builds the transport selected in the configuration.
+pub fn from_config(
+    config: &EmailTransportConfig,
+) -> Result<Arc<dyn EmailTransport>, EmailTransportError> {
+    let transport: Arc<dyn EmailTransport> = match config {
+        EmailTransportConfig::Smtp(smtp) => Arc::new(SmtpEmailTransport::new(smtp)?),
+        EmailTransportConfig::File { dir } => Arc::new(FileEmailTransport::new(dir.clone())?),
+        EmailTransportConfig::Log => Arc::new(LogEmailTransport),
+        EmailTransportConfig::Memory => Arc::new(InMemoryEmailTransport::default()),
+    };
+    Ok(transport)
+}
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(10);
+const SMTP_POOL_MAX_SIZE: u32 = 10;
+
+/// Async SMTP relay; connections are pooled and reused between messages.
+pub struct SmtpEmailTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpEmailTransport {
+    pub fn new(config: &SmtpConfig) -> Result<Self, EmailTransportError> {
+        let credentials = Credentials::new(config.username.clone(), config.password.clone());
+        let mailer = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            .port(config.port)
+            .credentials(credentials)
+            .timeout(Some(SMTP_TIMEOUT))
+            .pool_config(PoolConfig::new().max_size(SMTP_POOL_MAX_SIZE))
+            .build();
+        Ok(Self { mailer })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpEmailTransport {
+    async fn send(&self, message: &Message) -> Result<(), EmailTransportError> {
+        self.mailer.send(message.clone()).await?;
+        Ok(())
+    }
+}
+
+/// Writes each message to `<dir>/<timestamp>-<id>.eml`, which any mail client can open.
+pub struct FileEmailTransport {
+    dir: PathBuf,
+}
+
+impl FileEmailTransport {
+    pub fn new(dir: PathBuf) -> Result<Self, EmailTransportError> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for FileEmailTransport {
+    async fn send(&self, message: &Message) -> Result<(), EmailTransportError> {
+        let file_name = format!(
+            "{}-{}.eml",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            Uuid::new_v4()
+        );
+        let path = self.dir.join(file_name);
+        tokio::fs::write(&path, message.formatted()).await?;
+        info!("Email written to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Prints each message to the log. Only meant for local development: links and tokens end up in
+/// the output.
+pub struct LogEmailTransport;
+
+#[async_trait]
+impl EmailTransport for LogEmailTransport {
+    async fn send(&self, message: &Message) -> Result<(), EmailTransportError> {
+        info!(
+            "Outgoing email:\n{}",
+            String::from_utf8_lossy(&message.formatted())
+        );
+        Ok(())
+    }
+}
+
+/// Keeps sent messages in memory so tests can inspect them.
+#[derive(Default)]
+pub struct InMemoryEmailTransport {
+    messages: Mutex<Vec<Message>>,
+}
+
+impl InMemoryEmailTransport {
+    /// Returns the messages sent so far, oldest first.
+    pub fn messages(&self) -> Vec<Message> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.messages.lock().unwrap().clear();
+    }
+}
+
+#[async_trait]
+impl EmailTransport for InMemoryEmailTransport {
+    async fn send(&self, message: &Message) -> Result<(), EmailTransportError> {
+        self.messages.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
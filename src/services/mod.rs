@@ -1,3 +1,15 @@
+pub mod access_token_denylist;
+pub mod email_outbox;
 pub mod email_service;
+mod email_templates;
+pub mod email_transport;
+pub mod outbox_cipher;
 
+pub use access_token_denylist::AccessTokenDenylist;
+pub use email_outbox::EmailOutboxWorker;
 pub use email_service::EmailService;
+pub use email_transport::{
+    EmailTransport, EmailTransportError, FileEmailTransport, InMemoryEmailTransport,
+    LogEmailTransport, SmtpEmailTransport,
+};
+pub use outbox_cipher::OutboxCipher;
@@ -0,0 +1,89 @@
+use aes_gcm::{
+    Aes256Gcm, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use rand::RngCore;
+use tracing::warn;
+
+use crate::{
+    config::{OUTBOX_ENCRYPTION_KEY_LEN, OutboxConfig},
+    models::OutboxToken,
+};
+
+const NONCE_BYTES: usize = 12;
+
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum OutboxCipherError {
+    /// could not encrypt outbox token
+    Encrypt,
+    /// could not decrypt outbox token
+    Decrypt,
+}
+
+/// Encrypts the tokens in queued emails, so the outbox never holds a usable verification or
+/// reset link while a message waits for delivery.
+///
+/// Tokens are bound to their recipient, so a sealed token can't be moved to another message.
+pub struct OutboxCipher {
+    cipher: Aes256Gcm,
+}
+
+impl OutboxCipher {
+    pub fn from_config(config: &OutboxConfig) -> Self {
+        let key = config.encryption_key.unwrap_or_else(|| {
+            warn!(
+                "EMAIL_OUTBOX_ENCRYPTION_KEY is not set; using a random key, so emails queued \
+                 before a restart or by other instances can't be delivered"
+            );
+            let mut key = [0u8; OUTBOX_ENCRYPTION_KEY_LEN];
+            rand::rng().fill_bytes(&mut key);
+            key
+        });
+        Self {
+            cipher: Aes256Gcm::new(&key.into()),
+        }
+    }
+
+    pub fn seal(&self, recipient: &str, token: &str) -> Result<OutboxToken, OutboxCipherError> {
+        let mut nonce = [0u8; NONCE_BYTES];
+        rand::rng().fill_bytes(&mut nonce);
+
+        let payload = Payload {
+            msg: token.as_bytes(),
+            aad: recipient.as_bytes(),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&Nonce::from(nonce), payload)
+            .map_err(|_| OutboxCipherError::Encrypt)?;
+
+        Ok(OutboxToken::Sealed {
+            sealed: STANDARD.encode([nonce.as_slice(), &ciphertext].concat()),
+        })
+    }
+
+    pub fn open(&self, recipient: &str, token: &OutboxToken) -> Result<String, OutboxCipherError> {
+        let sealed = match token {
+            OutboxToken::Sealed { sealed } => sealed,
+            OutboxToken::Plain(token) => return Ok(token.clone()),
+        };
+
+        let sealed = STANDARD
+            .decode(sealed)
+            .map_err(|_| OutboxCipherError::Decrypt)?;
+        let (nonce, ciphertext) = sealed
+            .split_first_chunk::<NONCE_BYTES>()
+            .ok_or(OutboxCipherError::Decrypt)?;
+        let payload = Payload {
+            msg: ciphertext,
+            aad: recipient.as_bytes(),
+        };
+        let token = self
+            .cipher
+            .decrypt(&Nonce::from(*nonce), payload)
+            .map_err(|_| OutboxCipherError::Decrypt)?;
+
+        String::from_utf8(token).map_err(|_| OutboxCipherError::Decrypt)
+    }
+}
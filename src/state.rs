@@ -1,34 +1,98 @@
 use std::sync::Arc;
 
+use crate::auth::jwt::{JwtKeyError, JwtService};
+use crate::auth::mfa::MfaService;
+use crate::auth::oauth::{OAuthError, OAuthService};
+use crate::config::Config;
 use crate::metrics::Metrics;
 use crate::repositories::{
-    EmailVerificationRepository, EmailVerificationRepositoryTrait, PasswordResetRepository,
-    PasswordResetRepositoryTrait, RefreshTokenRepository, RefreshTokenRepositoryTrait,
-    UserRepository, UserRepositoryTrait,
+    ArticleRepository, ArticleRepositoryTrait, CommentRepository, CommentRepositoryTrait,
+    EmailOutboxRepository, EmailOutboxRepositoryTrait, EmailVerificationRepository,
+    EmailVerificationRepositoryTrait, MfaRepository, MfaRepositoryTrait, OAuthRepository,
+    OAuthRepositoryTrait, PasswordResetRepository, PasswordResetRepositoryTrait, ProfileRepository,
+    ProfileRepositoryTrait, RefreshTokenRepository, RefreshTokenRepositoryTrait,
+    RevokedAccessTokenRepository, RevokedAccessTokenRepositoryTrait, TagRepository,
+    TagRepositoryTrait, UnitOfWorkFactory, UnitOfWorkFactoryTrait, UserRepository,
+    UserRepositoryTrait,
+};
+use crate::services::{
+    AccessTokenDenylist, EmailService, EmailTransport, OutboxCipher, email_transport,
 };
-use crate::services::EmailService;
 use axum::extract::FromRef;
 use sqlx::PgPool;
-use tracing::{error, info};
+use tracing::info;
+
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub enum StateError {
+    /// could not connect to database
+    Database(#[from] sqlx::Error),
+    /// could not run database migrations
+    Migrate(#[from] sqlx::migrate::MigrateError),
+    /// could not initialize email transport
+    EmailTransport(#[from] crate::services::EmailTransportError),
+    /// could not initialize email service: {0}
+    Email(String),
+    /// could not load JWT keys
+    Jwt(#[from] JwtKeyError),
+    /// could not set up identity providers
+    OAuth(#[from] OAuthError),
+}
 
 #[derive(Clone, FromRef)]
 pub struct AppState {
+    pub config: Arc<Config>,
     pub db: PgPool,
     pub user_repository: Arc<dyn UserRepositoryTrait>,
     pub email_verification_repository: Arc<dyn EmailVerificationRepositoryTrait>,
     pub password_reset_repository: Arc<dyn PasswordResetRepositoryTrait>,
+    pub email_outbox_repository: Arc<dyn EmailOutboxRepositoryTrait>,
     pub refresh_token_repository: Arc<dyn RefreshTokenRepositoryTrait>,
+    pub mfa_repository: Arc<dyn MfaRepositoryTrait>,
+    pub oauth_repository: Arc<dyn OAuthRepositoryTrait>,
+    pub article_repository: Arc<dyn ArticleRepositoryTrait>,
+    pub profile_repository: Arc<dyn ProfileRepositoryTrait>,
+    pub comment_repository: Arc<dyn CommentRepositoryTrait>,
+    pub tag_repository: Arc<dyn TagRepositoryTrait>,
+    pub unit_of_work: Arc<dyn UnitOfWorkFactoryTrait>,
+    pub jwt: Arc<JwtService>,
+    /// `None` if two-factor authentication isn't configured
+    pub mfa: Option<Arc<MfaService>>,
+    pub oauth: Arc<OAuthService>,
+    pub access_token_denylist: Arc<AccessTokenDenylist>,
+    pub outbox_cipher: Arc<OutboxCipher>,
     pub email_service: Arc<EmailService>,
     pub metrics: Option<Metrics>,
 }
 
 impl AppState {
-    pub async fn new(database_url: &str, metrics: Option<Metrics>) -> Result<Self, sqlx::Error> {
+    pub async fn new(config: Config, metrics: Option<Metrics>) -> Result<Self, StateError> {
+        let email_transport = email_transport::from_config(&config.email.transport)?;
+        Self::with_email_transport(config, metrics, email_transport).await
+    }
+
+    /// Like [`AppState::new`], but delivers emails through the given transport instead of the
+    /// configured one, e.g. an [`crate::services::InMemoryEmailTransport`] in tests.
+    pub async fn with_email_transport(
+        config: Config,
+        metrics: Option<Metrics>,
+        email_transport: Arc<dyn EmailTransport>,
+    ) -> Result<Self, StateError> {
         // Create the database connection pool
-        let db = PgPool::connect(database_url).await?;
+        let db = PgPool::connect(&config.database.url).await?;
 
         sqlx::migrate!("./migrations").run(&db).await?;
 
+        Self::with_pool(config, db, metrics, email_transport).await
+    }
+
+    /// Like [`AppState::with_email_transport`], but on an existing pool whose database is already
+    /// migrated, e.g. the one `#[sqlx::test]` sets up.
+    pub async fn with_pool(
+        config: Config,
+        db: PgPool,
+        metrics: Option<Metrics>,
+        email_transport: Arc<dyn EmailTransport>,
+    ) -> Result<Self, StateError> {
         let user_repository: Arc<dyn UserRepositoryTrait> =
             Arc::new(UserRepository::new(db.clone()));
 
@@ -38,26 +102,71 @@ impl AppState {
         let password_reset_repository: Arc<dyn PasswordResetRepositoryTrait> =
             Arc::new(PasswordResetRepository::new(db.clone()));
 
+        let email_outbox_repository: Arc<dyn EmailOutboxRepositoryTrait> =
+            Arc::new(EmailOutboxRepository::new(db.clone()));
+
         let refresh_token_repository: Arc<dyn RefreshTokenRepositoryTrait> =
             Arc::new(RefreshTokenRepository::new(db.clone()));
 
-        info!("Initializing email service...");
-        let email_service = match EmailService::new() {
-            Ok(service) => Arc::new(service),
-            Err(e) => {
-                error!("Failed to initialize email service: {}", e);
-                error!("Make sure all SMTP env vars are set in .env");
-                panic!("Email service initialization failed");
-            }
-        };
+        let mfa_repository: Arc<dyn MfaRepositoryTrait> = Arc::new(MfaRepository::new(db.clone()));
+
+        let oauth_repository: Arc<dyn OAuthRepositoryTrait> =
+            Arc::new(OAuthRepository::new(db.clone()));
+
+        let article_repository: Arc<dyn ArticleRepositoryTrait> =
+            Arc::new(ArticleRepository::new(db.clone()));
+
+        let profile_repository: Arc<dyn ProfileRepositoryTrait> =
+            Arc::new(ProfileRepository::new(db.clone(), user_repository.clone()));
+
+        let comment_repository: Arc<dyn CommentRepositoryTrait> =
+            Arc::new(CommentRepository::new(db.clone()));
+
+        let tag_repository: Arc<dyn TagRepositoryTrait> = Arc::new(TagRepository::new(db.clone()));
+
+        let unit_of_work: Arc<dyn UnitOfWorkFactoryTrait> =
+            Arc::new(UnitOfWorkFactory::new(db.clone()));
+
+        let jwt = JwtService::from_config(&config.jwt)?;
+        let mfa = config.mfa.as_ref().map(MfaService::from_config);
+        let oauth = OAuthService::from_config(&config.oauth, &config.server.base_url)?;
+
+        let revoked_access_token_repository: Arc<dyn RevokedAccessTokenRepositoryTrait> =
+            Arc::new(RevokedAccessTokenRepository::new(db.clone()));
+        let access_token_denylist = AccessTokenDenylist::new(revoked_access_token_repository);
+        access_token_denylist.sync().await?;
+
+        let outbox_cipher = Arc::new(OutboxCipher::from_config(&config.email.outbox));
+        let email_service = EmailService::new(
+            email_transport,
+            outbox_cipher.clone(),
+            &config.email,
+            &config.server.base_url,
+        )
+        .map_err(|err| StateError::Email(err.to_string()))?;
+        info!("Email service initialized");
 
         Ok(Self {
+            config: Arc::new(config),
             db,
             user_repository,
             email_verification_repository,
             password_reset_repository,
+            email_outbox_repository,
             refresh_token_repository,
-            email_service,
+            mfa_repository,
+            oauth_repository,
+            article_repository,
+            profile_repository,
+            comment_repository,
+            tag_repository,
+            unit_of_work,
+            jwt: Arc::new(jwt),
+            mfa: mfa.map(Arc::new),
+            oauth: Arc::new(oauth),
+            access_token_denylist: Arc::new(access_token_denylist),
+            outbox_cipher,
+            email_service: Arc::new(email_service),
             metrics,
         })
     }
@@ -1,4 +1,5 @@
-pub mod token_generator;
-
-pub use token_generator::generate_verification_token;
+pub mod slug;
+pub mod tags;
 
+pub use slug::{slugify, unique_slug};
+pub use tags::{normalize_tags, validate_tags};
@@ -0,0 +1,42 @@
+use uuid::Uuid;
+
+// Length of `articles.slug`, VARCHAR(255) counts characters
+const MAX_SLUG_LEN: usize = 255;
+// "-" and eight hex digits
+const SUFFIX_LEN: usize = 9;
+
+pub fn slugify(title: &str) -> String {
+    // Lowercase the title and collapse every run of non-alphanumeric characters into a hyphen
+    // Example: "How to train your Dragon!" -> "how-to-train-your-dragon"
+    let mut slug = String::with_capacity(title.len());
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    // Lowercasing can expand characters, so the slug may be longer than the title
+    let slug = truncate_chars(&slug, MAX_SLUG_LEN).trim_end_matches('-');
+    if slug.is_empty() {
+        "article".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+pub fn unique_slug(base: &str) -> String {
+    // Append a short random suffix when the plain slug is already taken
+    // Example: "how-to-train-your-dragon-1f3a9c2e"
+    let suffix = Uuid::new_v4().simple().to_string();
+    let base = truncate_chars(base, MAX_SLUG_LEN - SUFFIX_LEN);
+    format!("{}-{}", base, &suffix[..SUFFIX_LEN - 1])
+}
+
+fn truncate_chars(s: &str, max_chars: usize) -> &str {
+    match s.char_indices().nth(max_chars) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
@@ -0,0 +1,36 @@
+use validator::ValidationError;
+
+// Length of `tags.name`, VARCHAR(64) counts characters
+const MAX_TAG_LEN: usize = 64;
+
+pub fn normalize_tags<S: AsRef<str>>(tags: &[S]) -> Vec<String> {
+    // Trim and lowercase every tag, dropping duplicates while keeping the order; empty tags were
+    // already rejected by `validate_tags`
+    // Example: [" Rust", "rust", "Axum "] -> ["rust", "axum"]
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.as_ref().trim().to_lowercase();
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+// Validates `tagList` as `normalize_tags` would store it, so a tag the database would refuse is
+// a validation error rather than a failed insert
+pub fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() {
+            return Err(
+                ValidationError::new("empty_tag").with_message("Tags cannot be empty".into())
+            );
+        }
+        if tag.chars().count() > MAX_TAG_LEN {
+            return Err(ValidationError::new("tag_too_long")
+                .with_message("Tags cannot exceed 64 characters".into()));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+// Shared setup for the integration tests. They run against the database in DATABASE_URL, in
+// which `#[sqlx::test]` creates a fresh, migrated database for every test.
+#![allow(dead_code)]
+
+use std::{sync::Arc, time::Duration};
+
+use jsonwebtoken::Algorithm;
+use realworld_axum_api::{
+    config::{
+        Config, DatabaseConfig, EmailConfig, EmailTransportConfig, JwtConfig, JwtKeyConfig,
+        MfaConfig, OAuthConfig, OtlpConfig, OutboxConfig, ServerConfig,
+    },
+    errors::Lang,
+    models::User,
+    services::InMemoryEmailTransport,
+    state::AppState,
+};
+use sqlx::PgPool;
+
+pub const BASE_URL: &str = "http://localhost:3000";
+
+pub fn test_config() -> Config {
+    Config {
+        server: ServerConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            cors_origins: Vec::new(),
+            request_timeout: Duration::from_secs(30),
+            base_url: BASE_URL.to_string(),
+            trust_proxy_headers: false,
+        },
+        // Unused, the state is built on the test's pool
+        database: DatabaseConfig { url: String::new() },
+        jwt: JwtConfig {
+            algorithm: Algorithm::HS256,
+            key: JwtKeyConfig::Secret("test-secret-that-is-at-least-32-bytes".to_string()),
+            key_id: None,
+            verification_keys: Vec::new(),
+            issuer: BASE_URL.to_string(),
+            audience: BASE_URL.to_string(),
+            access_token_ttl: Duration::from_secs(15 * 60),
+            revocation_sync_interval: Duration::from_secs(5),
+        },
+        mfa: Some(MfaConfig {
+            encryption_key: [7; 32],
+            issuer: "RealWorld".to_string(),
+            challenge_ttl: Duration::from_secs(300),
+        }),
+        oauth: OAuthConfig::default(),
+        email: EmailConfig {
+            from_email: "noreply@example.com".to_string(),
+            from_name: "RealWorld".to_string(),
+            transport: EmailTransportConfig::Memory,
+            outbox: OutboxConfig {
+                encryption_key: Some([9; 32]),
+                poll_interval: Duration::from_secs(5),
+                batch_size: 20,
+                max_attempts: 8,
+                backoff_base: Duration::from_secs(30),
+                backoff_max: Duration::from_secs(3600),
+            },
+        },
+        otlp: OtlpConfig {
+            endpoint: None,
+            token: None,
+        },
+    }
+}
+
+pub async fn test_state(db: PgPool, config: Config) -> AppState {
+    test_state_with_emails(db, config).await.0
+}
+
+// Also returns the transport, to inspect the emails the state sends
+pub async fn test_state_with_emails(
+    db: PgPool,
+    config: Config,
+) -> (AppState, Arc<InMemoryEmailTransport>) {
+    let emails = Arc::new(InMemoryEmailTransport::default());
+    let state = AppState::with_pool(config, db, None, emails.clone())
+        .await
+        .expect("app state");
+    (state, emails)
+}
+
+// The password hash is a placeholder, these users can't log in with a password
+pub async fn create_user(state: &AppState, username: &str) -> User {
+    state
+        .user_repository
+        .create(
+            username,
+            &format!("{username}@example.com"),
+            "not-a-bcrypt-hash",
+            Lang::default(),
+        )
+        .await
+        .expect("user")
+}
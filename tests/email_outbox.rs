@@ -0,0 +1,105 @@
+mod common;
+
+use axum::{Json, extract::State};
+use chrono::{Duration, Utc};
+use realworld_axum_api::{
+    errors::Lang,
+    handlers::forgot_password,
+    models::{OutboxEmail, OutboxToken},
+    schemas::password_reset_schemas::ForgotPasswordRequest,
+};
+use sqlx::PgPool;
+
+#[sqlx::test]
+async fn queued_tokens_are_encrypted_until_delivery(db: PgPool) {
+    let (state, emails) = common::test_state_with_emails(db, common::test_config()).await;
+    let user = common::create_user(&state, "alice").await;
+
+    let Json(_) = forgot_password(
+        State(state.clone()),
+        Json(ForgotPasswordRequest {
+            email: user.email.clone(),
+        }),
+    )
+    .await
+    .unwrap();
+
+    let messages = state
+        .email_outbox_repository
+        .claim_due(10, Utc::now() + Duration::minutes(5))
+        .await
+        .unwrap();
+    assert_eq!(messages.len(), 1);
+    let message = &messages[0];
+    let email = &message.email.0;
+    assert!(matches!(
+        serde_json::to_value(email).unwrap()["token"],
+        serde_json::Value::Object(_)
+    ));
+
+    // The sealed token only opens for the recipient it was queued for
+    assert!(
+        state
+            .email_service
+            .deliver("mallory@example.com", email)
+            .await
+            .is_err()
+    );
+    state
+        .email_service
+        .deliver(&message.recipient, email)
+        .await
+        .unwrap();
+
+    // Undo the quoted-printable encoding of the body, which wraps long lines and escapes `=`
+    let sent = emails.messages();
+    assert_eq!(sent.len(), 1);
+    let body = String::from_utf8(sent[0].formatted())
+        .unwrap()
+        .replace("=\r\n", "")
+        .replace("=3D", "=");
+    let token = body
+        .split("reset-password?token=")
+        .nth(1)
+        .and_then(|rest| {
+            rest.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .next()
+        })
+        .expect("reset link");
+
+    let reset = state
+        .password_reset_repository
+        .find_by_token(token)
+        .await
+        .unwrap()
+        .expect("stored reset token");
+    assert_eq!(reset.user_id, user.id);
+}
+
+#[sqlx::test]
+async fn plaintext_tokens_queued_before_encryption_are_still_delivered(db: PgPool) {
+    let (state, emails) = common::test_state_with_emails(db, common::test_config()).await;
+
+    let email: OutboxEmail = serde_json::from_value(serde_json::json!({
+        "kind": "verification",
+        "username": "alice",
+        "token": "0123456789abcdef",
+    }))
+    .unwrap();
+    assert_eq!(
+        email,
+        OutboxEmail::Verification {
+            username: "alice".to_string(),
+            token: OutboxToken::Plain("0123456789abcdef".to_string()),
+            lang: Lang::default(),
+        }
+    );
+
+    state
+        .email_service
+        .deliver("alice@example.com", &email)
+        .await
+        .unwrap();
+    let body = String::from_utf8(emails.messages()[0].formatted()).unwrap();
+    assert!(body.contains("0123456789abcdef"));
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use axum::{Json, extract::State, http::StatusCode};
+use chrono::{Duration, Utc};
+use realworld_axum_api::{
+    auth::{mfa::generate_recovery_codes, tokens::TokenKind},
+    handlers::verify_mfa_login,
+    models::ClientInfo,
+    schemas::VerifyMfaLoginRequest,
+    state::AppState,
+};
+use sqlx::PgPool;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+// Attempts a challenge allows, as set in the handler
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+// Enables two-factor authentication and starts a login challenge; the recovery codes give a
+// code that is known to be correct
+async fn start_challenge(state: &AppState, user_id: Uuid) -> (String, Vec<String>) {
+    let mfa = state.mfa.as_deref().unwrap();
+    let secret = mfa.encrypt_secret(user_id, &mfa.generate_secret()).unwrap();
+    state
+        .mfa_repository
+        .start_enrollment(user_id, &secret)
+        .await
+        .unwrap();
+    assert!(state.mfa_repository.enable_totp(user_id, 0).await.unwrap());
+    let recovery_codes = generate_recovery_codes();
+    state
+        .mfa_repository
+        .replace_recovery_codes(user_id, &recovery_codes)
+        .await
+        .unwrap();
+
+    let mfa_token = TokenKind::MfaChallenge.generate();
+    state
+        .mfa_repository
+        .create_challenge(user_id, &mfa_token, Utc::now() + Duration::minutes(5))
+        .await
+        .unwrap();
+
+    (mfa_token, recovery_codes)
+}
+
+async fn verify(state: &AppState, mfa_token: &str, code: &str) -> Result<(), StatusCode> {
+    verify_mfa_login(
+        State(state.clone()),
+        ClientInfo::default(),
+        Json(VerifyMfaLoginRequest {
+            mfa_token: mfa_token.to_string(),
+            code: code.to_string(),
+        }),
+    )
+    .await
+    .map(|_| ())
+    .map_err(|err| err.status())
+}
+
+#[sqlx::test]
+async fn parallel_wrong_codes_share_the_attempt_limit(db: PgPool) {
+    let state = common::test_state(db.clone(), common::test_config()).await;
+    let user = common::create_user(&state, "alice").await;
+
+    let (mfa_token, recovery_codes) = start_challenge(&state, user.id).await;
+
+    let mut requests = JoinSet::new();
+    for _ in 0..20 {
+        let (state, mfa_token) = (state.clone(), mfa_token.clone());
+        requests.spawn(async move { verify(&state, &mfa_token, "wrong-code").await });
+    }
+    for result in requests.join_all().await {
+        assert_eq!(result, Err(StatusCode::UNAUTHORIZED));
+    }
+
+    let failed_attempts: i32 =
+        sqlx::query_scalar("SELECT failed_attempts FROM mfa_challenges WHERE user_id = $1")
+            .bind(user.id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(failed_attempts, MAX_CHALLENGE_ATTEMPTS);
+
+    // Out of attempts, even the right code doesn't get through
+    assert_eq!(
+        verify(&state, &mfa_token, &recovery_codes[0]).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+}
+
+#[sqlx::test]
+async fn correct_code_completes_the_challenge_once(db: PgPool) {
+    let state = common::test_state(db, common::test_config()).await;
+    let user = common::create_user(&state, "alice").await;
+
+    let (mfa_token, recovery_codes) = start_challenge(&state, user.id).await;
+
+    // A wrong code leaves the challenge in place
+    assert_eq!(
+        verify(&state, &mfa_token, "wrong-code").await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+    assert_eq!(verify(&state, &mfa_token, &recovery_codes[0]).await, Ok(()));
+    assert_eq!(
+        verify(&state, &mfa_token, &recovery_codes[1]).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+}
@@ -0,0 +1,295 @@
+mod common;
+
+use std::collections::HashMap;
+
+use axum::{
+    Form, Json, Router,
+    extract::{Path, Query, State},
+    http::{
+        HeaderMap, StatusCode,
+        header::{COOKIE, LOCATION, SET_COOKIE},
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_extra::extract::CookieJar;
+use realworld_axum_api::{
+    config::{Config, OAuthConfig, OAuthProviderConfig, OAuthProviderKind},
+    errors::Lang,
+    handlers::{oauth_authorize, oauth_callback, oauth_exchange},
+    models::ClientInfo,
+    schemas::{LoginResult, OAuthCallbackQuery, OAuthExchangeRequest},
+    state::AppState,
+};
+use serde_json::json;
+use sqlx::PgPool;
+
+const FRONTEND_URL: &str = "http://localhost:5173/oauth";
+// Codes the mock token endpoint accepts, and fails on as if it were down
+const GOOD_CODE: &str = "good-code";
+const BROKEN_CODE: &str = "broken-code";
+
+// A GitHub stand-in: the token endpoint and the two API calls the login makes. The email is
+// reported in another case than users register it with.
+async fn mock_github() -> String {
+    async fn token(Form(form): Form<HashMap<String, String>>) -> Response {
+        match form.get("code").map(String::as_str) {
+            Some(GOOD_CODE) if form.contains_key("code_verifier") => Json(json!({
+                "access_token": "gho_test",
+                "token_type": "bearer",
+            }))
+            .into_response(),
+            Some(BROKEN_CODE) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "<h1>oops</h1>").into_response()
+            }
+            _ => (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "invalid_grant" })),
+            )
+                .into_response(),
+        }
+    }
+
+    let app = Router::new()
+        .route("/login/oauth/access_token", post(token))
+        .route(
+            "/api/user",
+            get(|| async { Json(json!({ "id": 42, "login": "octocat" })) }),
+        )
+        .route(
+            "/api/user/emails",
+            get(|| async {
+                Json(json!([
+                    { "email": "OctoCat@Example.com", "primary": true, "verified": true },
+                ]))
+            }),
+        );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+fn oauth_config(github_url: &str) -> Config {
+    Config {
+        oauth: OAuthConfig {
+            providers: vec![OAuthProviderConfig {
+                name: "github".to_string(),
+                kind: OAuthProviderKind::GitHub {
+                    auth_url: format!("{github_url}/login/oauth/authorize"),
+                    token_url: format!("{github_url}/login/oauth/access_token"),
+                    api_url: format!("{github_url}/api"),
+                },
+                client_id: "client-id".to_string(),
+                client_secret: "client-secret".to_string(),
+                scopes: vec!["read:user".to_string(), "user:email".to_string()],
+            }],
+            frontend_url: Some(FRONTEND_URL.to_string()),
+        },
+        ..common::test_config()
+    }
+}
+
+// Starts a login; returns the state the provider would send back, and the browser's cookies
+async fn authorize(state: &AppState) -> (String, HeaderMap) {
+    let response = oauth_authorize(
+        State(state.clone()),
+        Path("github".to_string()),
+        CookieJar::new(),
+    )
+    .await
+    .unwrap()
+    .into_response();
+
+    let location = response.headers()[LOCATION].to_str().unwrap();
+    let csrf_state = reqwest::Url::parse(location)
+        .unwrap()
+        .query_pairs()
+        .find(|(name, _)| name == "state")
+        .map(|(_, value)| value.into_owned())
+        .expect("state in authorization URL");
+
+    let set_cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+    let cookie = set_cookie.split(';').next().unwrap();
+    let mut cookies = HeaderMap::new();
+    cookies.insert(COOKIE, cookie.parse().unwrap());
+
+    (csrf_state, cookies)
+}
+
+async fn callback(
+    state: &AppState,
+    cookies: &HeaderMap,
+    query: OAuthCallbackQuery,
+) -> Result<Response, StatusCode> {
+    oauth_callback(
+        State(state.clone()),
+        Path("github".to_string()),
+        CookieJar::from_headers(cookies),
+        Query(query),
+    )
+    .await
+    .map(IntoResponse::into_response)
+    .map_err(|err| err.status())
+}
+
+fn code(code: &str, csrf_state: &str) -> OAuthCallbackQuery {
+    OAuthCallbackQuery {
+        code: Some(code.to_string()),
+        state: Some(csrf_state.to_string()),
+        error: None,
+    }
+}
+
+#[sqlx::test]
+async fn login_links_an_account_registered_in_another_case(db: PgPool) {
+    let github_url = mock_github().await;
+    let state = common::test_state(db, oauth_config(&github_url)).await;
+
+    let user = state
+        .user_repository
+        .create(
+            "octo",
+            "Octocat@example.com",
+            "not-a-bcrypt-hash",
+            Lang::default(),
+        )
+        .await
+        .unwrap();
+    state
+        .email_verification_repository
+        .verify_user_email(user.id)
+        .await
+        .unwrap();
+
+    let (csrf_state, cookies) = authorize(&state).await;
+    let response = callback(&state, &cookies, code(GOOD_CODE, &csrf_state))
+        .await
+        .unwrap();
+    let location = response.headers()[LOCATION].to_str().unwrap();
+    let login_code = location.split("code=").nth(1).unwrap().to_string();
+
+    let Json(LoginResult::Authenticated(login)) = oauth_exchange(
+        State(state.clone()),
+        ClientInfo::default(),
+        Json(OAuthExchangeRequest { code: login_code }),
+    )
+    .await
+    .unwrap() else {
+        panic!("expected tokens");
+    };
+    assert_eq!(login.user.username, "octo");
+}
+
+#[sqlx::test]
+async fn login_goes_through_the_frontend_with_a_one_time_code(db: PgPool) {
+    let github_url = mock_github().await;
+    let state = common::test_state(db, oauth_config(&github_url)).await;
+
+    let (csrf_state, cookies) = authorize(&state).await;
+    let response = callback(&state, &cookies, code(GOOD_CODE, &csrf_state))
+        .await
+        .unwrap();
+
+    // The browser is sent to the frontend with a code, never with the tokens
+    let location = response.headers()[LOCATION].to_str().unwrap();
+    assert!(location.starts_with(&format!("{FRONTEND_URL}?code=ol_")));
+    let login_code = location.split("code=").nth(1).unwrap().to_string();
+
+    let exchange = |code: String| {
+        oauth_exchange(
+            State(state.clone()),
+            ClientInfo::default(),
+            Json(OAuthExchangeRequest { code }),
+        )
+    };
+    let Json(LoginResult::Authenticated(login)) = exchange(login_code.clone()).await.unwrap()
+    else {
+        panic!("expected tokens");
+    };
+    assert_eq!(login.user.email, "octocat@example.com");
+    assert_eq!(login.user.username, "octocat");
+
+    // The code only works once
+    assert_eq!(
+        exchange(login_code).await.unwrap_err().status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+// Without the cookie, an attacker could have the victim finish a login the attacker started
+#[sqlx::test]
+async fn callback_from_another_browser_is_rejected(db: PgPool) {
+    let github_url = mock_github().await;
+    let state = common::test_state(db, oauth_config(&github_url)).await;
+
+    let (csrf_state, _) = authorize(&state).await;
+    let (_, other_cookies) = authorize(&state).await;
+
+    for cookies in [HeaderMap::new(), other_cookies] {
+        assert_eq!(
+            callback(&state, &cookies, code(GOOD_CODE, &csrf_state))
+                .await
+                .unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
+
+#[sqlx::test]
+async fn refusals_are_client_errors_and_provider_failures_bad_gateway(db: PgPool) {
+    let github_url = mock_github().await;
+    let state = common::test_state(db, oauth_config(&github_url)).await;
+
+    let error = |error: &str| OAuthCallbackQuery {
+        code: None,
+        state: None,
+        error: Some(error.to_string()),
+    };
+    let (_, cookies) = authorize(&state).await;
+    assert_eq!(
+        callback(&state, &cookies, error("access_denied"))
+            .await
+            .unwrap_err(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        callback(&state, &cookies, error("temporarily_unavailable"))
+            .await
+            .unwrap_err(),
+        StatusCode::BAD_GATEWAY
+    );
+    assert_eq!(
+        callback(
+            &state,
+            &cookies,
+            OAuthCallbackQuery {
+                code: Some(GOOD_CODE.to_string()),
+                state: None,
+                error: None,
+            }
+        )
+        .await
+        .unwrap_err(),
+        StatusCode::BAD_REQUEST
+    );
+
+    // The token endpoint turns down the code
+    let (csrf_state, cookies) = authorize(&state).await;
+    assert_eq!(
+        callback(&state, &cookies, code("made-up-code", &csrf_state))
+            .await
+            .unwrap_err(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // The token endpoint fails
+    let (csrf_state, cookies) = authorize(&state).await;
+    assert_eq!(
+        callback(&state, &cookies, code(BROKEN_CODE, &csrf_state))
+            .await
+            .unwrap_err(),
+        StatusCode::BAD_GATEWAY
+    );
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use axum::{Json, extract::State, http::StatusCode};
+use chrono::{Duration, Utc};
+use realworld_axum_api::{
+    auth::tokens::TokenKind, handlers::reset_password, models::ClientInfo,
+    schemas::password_reset_schemas::ResetPasswordRequest, state::AppState,
+};
+use sqlx::PgPool;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+const CONCURRENT_REQUESTS: usize = 10;
+
+async fn reset(state: &AppState, token: &str, new_password: &str) -> Result<(), StatusCode> {
+    reset_password(
+        State(state.clone()),
+        Json(ResetPasswordRequest {
+            token: token.to_string(),
+            new_password: new_password.to_string(),
+        }),
+    )
+    .await
+    .map(|_| ())
+    .map_err(|err| err.status())
+}
+
+#[sqlx::test]
+async fn a_token_sets_only_one_password(db: PgPool) {
+    let state = common::test_state(db, common::test_config()).await;
+    let user = common::create_user(&state, "alice").await;
+
+    let token = TokenKind::PasswordReset.generate();
+    state
+        .password_reset_repository
+        .create_token(user.id, &token, Utc::now() + Duration::hours(1))
+        .await
+        .unwrap();
+
+    let mut requests = JoinSet::new();
+    for i in 0..CONCURRENT_REQUESTS {
+        let (state, token) = (state.clone(), token.clone());
+        requests.spawn(async move { reset(&state, &token, &format!("new-password-{i}")).await });
+    }
+    let results = requests.join_all().await;
+
+    assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+    assert!(
+        results
+            .iter()
+            .all(|result| matches!(result, Ok(()) | Err(StatusCode::NOT_FOUND)))
+    );
+}
+
+#[sqlx::test]
+async fn an_expired_token_is_gone(db: PgPool) {
+    let state = common::test_state(db, common::test_config()).await;
+    let user = common::create_user(&state, "alice").await;
+
+    let token = TokenKind::PasswordReset.generate();
+    state
+        .password_reset_repository
+        .create_token(user.id, &token, Utc::now() - Duration::minutes(1))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        reset(&state, &token, "new-password").await,
+        Err(StatusCode::GONE)
+    );
+    // Expired tokens are cleaned up
+    assert_eq!(
+        reset(&state, &token, "new-password").await,
+        Err(StatusCode::NOT_FOUND)
+    );
+}
+
+#[sqlx::test]
+async fn reset_ends_every_session(db: PgPool) {
+    let state = common::test_state(db, common::test_config()).await;
+    let user = common::create_user(&state, "alice").await;
+
+    for _ in 0..2 {
+        state
+            .refresh_token_repository
+            .create_token(
+                user.id,
+                &TokenKind::Refresh.generate(),
+                Uuid::new_v4(),
+                Utc::now() + Duration::days(7),
+                &ClientInfo::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let token = TokenKind::PasswordReset.generate();
+    state
+        .password_reset_repository
+        .create_token(user.id, &token, Utc::now() + Duration::hours(1))
+        .await
+        .unwrap();
+    assert_eq!(reset(&state, &token, "new-password").await, Ok(()));
+
+    assert!(
+        state
+            .refresh_token_repository
+            .list_sessions(user.id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use axum::{Json, extract::State};
+use chrono::{Duration, Utc};
+use realworld_axum_api::{
+    auth::tokens::TokenKind,
+    handlers::refresh_token,
+    models::ClientInfo,
+    repositories::{RefreshTokenRepository, RefreshTokenRepositoryTrait},
+    schemas::RefreshTokenRequest,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const CONCURRENT_REQUESTS: usize = 20;
+
+#[sqlx::test]
+async fn concurrent_consumption_has_exactly_one_winner(db: PgPool) {
+    let state = common::test_state(db.clone(), common::test_config()).await;
+    let user = common::create_user(&state, "alice").await;
+
+    let token = TokenKind::Refresh.generate();
+    state
+        .refresh_token_repository
+        .create_token(
+            user.id,
+            &token,
+            Uuid::new_v4(),
+            Utc::now() + Duration::minutes(15),
+            &ClientInfo::default(),
+        )
+        .await
+        .unwrap();
+
+    // Separate repositories on the shared pool, so the updates run on their own connections
+    let tasks: Vec<_> = (0..CONCURRENT_REQUESTS)
+        .map(|_| {
+            let repository = RefreshTokenRepository::new(db.clone());
+            let token = token.clone();
+            tokio::spawn(async move { repository.consume_token(&token).await })
+        })
+        .collect();
+
+    let mut winners = 0;
+    for task in tasks {
+        if task.await.unwrap().unwrap().is_some() {
+            winners += 1;
+        }
+    }
+    assert_eq!(winners, 1);
+
+    let consumed = state
+        .refresh_token_repository
+        .find_by_token(&token)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(consumed.is_used);
+}
+
+#[sqlx::test]
+async fn concurrent_refreshes_rotate_once_and_reuse_revokes_the_family(db: PgPool) {
+    let state = common::test_state(db.clone(), common::test_config()).await;
+    let user = common::create_user(&state, "bob").await;
+
+    let token = TokenKind::Refresh.generate();
+    let first = state
+        .refresh_token_repository
+        .create_token(
+            user.id,
+            &token,
+            Uuid::new_v4(),
+            Utc::now() + Duration::minutes(15),
+            &ClientInfo::default(),
+        )
+        .await
+        .unwrap();
+
+    let tasks: Vec<_> = (0..CONCURRENT_REQUESTS)
+        .map(|_| {
+            let state = state.clone();
+            let request = RefreshTokenRequest {
+                refresh_token: token.clone(),
+            };
+            tokio::spawn(async move {
+                refresh_token(State(state), ClientInfo::default(), Json(request)).await
+            })
+        })
+        .collect();
+
+    let mut rotated = Vec::new();
+    for task in tasks {
+        if let Ok(Json(response)) = task.await.unwrap() {
+            rotated.push(response);
+        }
+    }
+    assert_eq!(rotated.len(), 1, "exactly one refresh may rotate the token");
+
+    // The losers presented a used token, which revokes the family including the new token
+    let remaining: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM refresh_tokens WHERE family_id = $1")
+            .bind(first.family_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(remaining, 0);
+    assert!(
+        state
+            .refresh_token_repository
+            .find_by_token(&rotated[0].refresh_token)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // The new access token is revoked with its family
+    let claims = state.jwt.validate_token(&rotated[0].access_token).unwrap();
+    let jti = claims.jti.parse().unwrap();
+    assert!(state.access_token_denylist.is_revoked(&jti));
+}
+
+// Tokens issued before the prefixed format keep working until they expire
+#[sqlx::test]
+async fn legacy_refresh_tokens_still_rotate(db: PgPool) {
+    let state = common::test_state(db, common::test_config()).await;
+    let user = common::create_user(&state, "carol").await;
+
+    let token = Uuid::new_v4().to_string();
+    assert!(TokenKind::Refresh.is_well_formed(&token));
+    assert!(!TokenKind::EmailVerification.is_well_formed(&token));
+    state
+        .refresh_token_repository
+        .create_token(
+            user.id,
+            &token,
+            Uuid::new_v4(),
+            Utc::now() + Duration::minutes(15),
+            &ClientInfo::default(),
+        )
+        .await
+        .unwrap();
+
+    let Json(response) = refresh_token(
+        State(state.clone()),
+        ClientInfo::default(),
+        Json(RefreshTokenRequest {
+            refresh_token: token,
+        }),
+    )
+    .await
+    .unwrap();
+    assert!(TokenKind::Refresh.is_well_formed(&response.refresh_token));
+    assert!(response.refresh_token.starts_with("rt_"));
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use axum::{Router, body::Body, http::Request, routing::get};
+use chrono::{Duration, Utc};
+use realworld_axum_api::{
+    auth::{middleware::request_span, tokens::TokenKind},
+    handlers::verify_email,
+};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use tower_http::trace::TraceLayer;
+use tracing::Level;
+use tracing_subscriber::fmt::{MakeWriter, format::FmtSpan};
+
+// Collects everything the subscriber writes
+#[derive(Clone, Default)]
+struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Logs {
+    type Writer = Logs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[sqlx::test]
+async fn verify_email_requests_do_not_log_the_token(db: PgPool) {
+    let state = common::test_state(db, common::test_config()).await;
+    let user = common::create_user(&state, "alice").await;
+
+    let token = TokenKind::EmailVerification.generate();
+    state
+        .email_verification_repository
+        .create_token(user.id, &token, Utc::now() + Duration::hours(1))
+        .await
+        .unwrap();
+
+    let logs = Logs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(Level::TRACE)
+        .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
+        .with_ansi(false)
+        .with_writer(logs.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = Router::new()
+        .route("/api/auth/verify-email", get(verify_email))
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
+        .with_state(state);
+    let response = app
+        .oneshot(
+            Request::get(format!("/api/auth/verify-email?token={token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(logs.contains("/api/auth/verify-email"), "{logs}");
+    assert!(!logs.contains(&token), "{logs}");
+}